@@ -2,11 +2,14 @@ function(event) {
   function handle_event(symbol, value) {
     switch (symbol) {
       case "limiter":
-        const limiter = event.icon.find("[mod-port-symbol=limiter]");
+      case "sync":
+      case "tap":
+      case "freeze":
+        const toggle = event.icon.find("[mod-port-symbol=" + symbol + "]");
         if(value == 1) {
-          limiter.addClass("on");
+          toggle.addClass("on");
         } else {
-          limiter.removeClass("on");
+          toggle.removeClass("on");
         }
         break;
       default:
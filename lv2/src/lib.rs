@@ -1,7 +1,16 @@
 extern crate lv2;
 extern crate repeat;
 use lv2::prelude::*;
-use repeat::Repeat;
+use repeat::{
+  CrossfadeCurve, DriveCurve, EnvelopeShape, FeedbackMode, Interpolation, LfoShape, Params,
+  Pattern, Repeat, ReverseMode, SpreadMode, TapTempo, TimeMode, CUSTOM_ENVELOPE_POINTS,
+  DEFAULT_MAX_DELAY_TIME,
+};
+
+/// The tempo that's used for synced repeats when the host doesn't provide one.
+const DEFAULT_TEMPO: f32 = 120.;
+/// The number of samples that are processed at once.
+const BLOCK_SIZE: usize = 64;
 
 #[derive(PortCollection)]
 struct Ports {
@@ -12,49 +21,311 @@ struct Ports {
   limiter: InputPort<InPlaceControl>,
   input: InputPort<InPlaceAudio>,
   output: OutputPort<InPlaceAudio>,
+  output_right: OutputPort<InPlaceAudio>,
+  spread: InputPort<InPlaceControl>,
+  spread_mode: InputPort<InPlaceControl>,
+  mix: InputPort<InPlaceControl>,
+  dry: InputPort<InPlaceControl>,
+  wet: InputPort<InPlaceControl>,
+  sync: InputPort<InPlaceControl>,
+  division: InputPort<InPlaceControl>,
+  division_type: InputPort<InPlaceControl>,
+  control: InputPort<AtomPort>,
+  tap: InputPort<InPlaceControl>,
+  tapped_tempo: OutputPort<InPlaceControl>,
+  tone: InputPort<InPlaceControl>,
+  tone_skew: InputPort<InPlaceControl>,
+  pitch: InputPort<InPlaceControl>,
+  reverse_mode: InputPort<InPlaceControl>,
+  freeze: InputPort<InPlaceControl>,
+  lfo_shape: InputPort<InPlaceControl>,
+  lfo_rate: InputPort<InPlaceControl>,
+  lfo_depth: InputPort<InPlaceControl>,
+  lfo_phase_offset: InputPort<InPlaceControl>,
+  interpolation: InputPort<InPlaceControl>,
+  feedback_mode: InputPort<InPlaceControl>,
+  drive: InputPort<InPlaceControl>,
+  drive_curve: InputPort<InPlaceControl>,
+  pattern: InputPort<InPlaceControl>,
+  pattern_amount: InputPort<InPlaceControl>,
+  euclidean_steps: InputPort<InPlaceControl>,
+  pattern_seed: InputPort<InPlaceControl>,
+  envelope_shape: InputPort<InPlaceControl>,
+  crossfade_time: InputPort<InPlaceControl>,
+  crossfade_curve: InputPort<InPlaceControl>,
+  time_mode: InputPort<InPlaceControl>,
+  glide_rate: InputPort<InPlaceControl>,
+  envelope_point_1: InputPort<InPlaceControl>,
+  envelope_point_2: InputPort<InPlaceControl>,
+  envelope_point_3: InputPort<InPlaceControl>,
+  envelope_point_4: InputPort<InPlaceControl>,
+  envelope_point_5: InputPort<InPlaceControl>,
+  envelope_point_6: InputPort<InPlaceControl>,
+  envelope_point_7: InputPort<InPlaceControl>,
+  envelope_point_8: InputPort<InPlaceControl>,
+}
+
+#[derive(FeatureCollection)]
+pub struct Features<'a> {
+  map: LV2Map<'a>,
+}
+
+#[derive(URIDCollection)]
+pub struct URIDs {
+  atom: AtomURIDCollection,
+  unit: UnitURIDCollection,
+  time: TimeURIDCollection,
 }
 
 #[uri("https://github.com/davemollen/dm-Repeat")]
 struct DmRepeat {
   repeat: Repeat,
   is_active: bool,
+  urids: URIDs,
+  tempo: Option<f32>,
+  tap_tempo: TapTempo,
+  previous_freq: f32,
+  custom_envelope: [f32; CUSTOM_ENVELOPE_POINTS],
+}
+
+impl DmRepeat {
+  /// Reads the tempo from the time:Position objects the host sends.
+  fn update_tempo(&mut self, ports: &mut Ports) {
+    let control_sequence = match ports
+      .control
+      .read(self.urids.atom.sequence, self.urids.unit.beat)
+    {
+      Some(sequence) => sequence,
+      None => return,
+    };
+
+    for (_, message) in control_sequence {
+      if let Some((object_header, object_reader)) = message.read(self.urids.atom.object, ()) {
+        if object_header.otype == self.urids.time.position_class {
+          for (property_header, property) in object_reader {
+            if property_header.key == self.urids.time.beats_per_minute {
+              if let Some(tempo) = property.read(self.urids.atom.float, ()) {
+                self.tempo = Some(tempo);
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+
+  fn get_time(&self, ports: &Ports) -> f32 {
+    if ports.sync.get() == 1. {
+      let beat_time = 60000. / self.tempo.unwrap_or(DEFAULT_TEMPO);
+      let beats = 4. / 2_f32.powi(ports.division.get() as i32);
+      let factor = match ports.division_type.get() as u32 {
+        1 => 1.5,
+        2 => 2. / 3.,
+        _ => 1.,
+      };
+      beat_time * beats * factor
+    } else {
+      self
+        .tap_tempo
+        .get_time()
+        .unwrap_or_else(|| (ports.freq.get()).recip() * 1000.)
+    }
+  }
+
+  /// Moving the freq parameter overrides the tapped time.
+  fn update_tap_tempo(&mut self, ports: &mut Ports, sample_count: u32) {
+    let freq = ports.freq.get();
+    if freq != self.previous_freq {
+      self.tap_tempo.reset();
+      self.previous_freq = freq;
+    }
+
+    self
+      .tap_tempo
+      .process(ports.tap.get() == 1., sample_count as usize);
+    ports
+      .tapped_tempo
+      .set(self.tap_tempo.get_tempo().unwrap_or(0.));
+  }
+
+  /// The custom envelope is only passed on when a point has moved, because that makes the repeat engine update the taps.
+  fn update_custom_envelope(&mut self, ports: &Ports) {
+    let custom_envelope = [
+      ports.envelope_point_1.get(),
+      ports.envelope_point_2.get(),
+      ports.envelope_point_3.get(),
+      ports.envelope_point_4.get(),
+      ports.envelope_point_5.get(),
+      ports.envelope_point_6.get(),
+      ports.envelope_point_7.get(),
+      ports.envelope_point_8.get(),
+    ]
+    .map(|gain| gain * 0.01);
+
+    if custom_envelope != self.custom_envelope {
+      self.repeat.set_custom_envelope(&custom_envelope);
+      self.custom_envelope = custom_envelope;
+    }
+  }
+
+  fn get_params(&self, ports: &Ports) -> Params {
+    Params {
+      time: self.get_time(ports),
+      repeats: ports.repeats.get() as usize,
+      feedback: ports.feedback.get() * 0.01,
+      feedback_mode: match ports.feedback_mode.get() as u32 {
+        1 => FeedbackMode::Recirculating,
+        _ => FeedbackMode::Simulated,
+      },
+      envelope_shape: match ports.envelope_shape.get() as u32 {
+        1 => EnvelopeShape::Linear,
+        2 => EnvelopeShape::Bell,
+        3 => EnvelopeShape::Gate,
+        4 => EnvelopeShape::Random,
+        5 => EnvelopeShape::Custom,
+        _ => EnvelopeShape::Exponential,
+      },
+      skew: ports.skew.get() * 0.01,
+      // The custom pattern is left out, because its taps are drawn in the nih-plug editor and saved with its state.
+      pattern: match ports.pattern.get() as u32 {
+        1 => Pattern::Euclidean,
+        2 => Pattern::Swing,
+        3 => Pattern::Golden,
+        4 => Pattern::Random,
+        _ => Pattern::Skew,
+      },
+      pattern_amount: ports.pattern_amount.get() * 0.01,
+      euclidean_steps: ports.euclidean_steps.get() as usize,
+      pattern_seed: ports.pattern_seed.get() as u32,
+      tone: ports.tone.get() * 0.01,
+      tone_skew: ports.tone_skew.get() * 0.01,
+      pitch: ports.pitch.get(),
+      reverse_mode: match ports.reverse_mode.get() as u32 {
+        1 => ReverseMode::All,
+        2 => ReverseMode::Alternate,
+        _ => ReverseMode::Off,
+      },
+      lfo_shape: match ports.lfo_shape.get() as u32 {
+        1 => LfoShape::Triangle,
+        2 => LfoShape::Random,
+        _ => LfoShape::Sine,
+      },
+      lfo_rate: ports.lfo_rate.get(),
+      lfo_depth: ports.lfo_depth.get(),
+      lfo_phase_offset: ports.lfo_phase_offset.get() * 0.01,
+      interpolation: match ports.interpolation.get() as u32 {
+        1 => Interpolation::Linear,
+        2 => Interpolation::Cosine,
+        3 => Interpolation::Cubic,
+        4 => Interpolation::Spline,
+        _ => Interpolation::Step,
+      },
+      time_mode: match ports.time_mode.get() as u32 {
+        1 => TimeMode::Tape,
+        _ => TimeMode::Crossfade,
+      },
+      glide_rate: ports.glide_rate.get(),
+      crossfade_time: ports.crossfade_time.get(),
+      crossfade_curve: match ports.crossfade_curve.get() as u32 {
+        0 => CrossfadeCurve::Linear,
+        2 => CrossfadeCurve::EqualPower,
+        _ => CrossfadeCurve::Smooth,
+      },
+      drive: ports.drive.get() * 0.01,
+      drive_curve: match ports.drive_curve.get() as u32 {
+        1 => DriveCurve::Atan,
+        2 => DriveCurve::SoftClip,
+        3 => DriveCurve::Tube,
+        _ => DriveCurve::Tanh,
+      },
+      spread: ports.spread.get() * 0.01,
+      spread_mode: match ports.spread_mode.get() as u32 {
+        1 => SpreadMode::Linear,
+        2 => SpreadMode::Random,
+        3 => SpreadMode::CenterOut,
+        _ => SpreadMode::PingPong,
+      },
+      limiter: ports.limiter.get() == 1.,
+      freeze: ports.freeze.get() == 1.,
+      mix: ports.mix.get() * 0.01,
+      dry: Self::dbtoa(ports.dry.get()),
+      wet: Self::dbtoa(ports.wet.get()),
+    }
+  }
+
+  /// Builds for hosts with little memory, like MOD devices, can set the maximum delay time in milliseconds with the
+  /// `DM_REPEAT_MAX_DELAY_TIME` environment variable at compile time, so the delay lines are allocated smaller.
+  fn get_max_delay_time() -> f32 {
+    option_env!("DM_REPEAT_MAX_DELAY_TIME")
+      .and_then(|max_delay_time| max_delay_time.parse().ok())
+      .unwrap_or(DEFAULT_MAX_DELAY_TIME)
+  }
+
+  fn dbtoa(db: f32) -> f32 {
+    10_f32.powf(db * 0.05)
+  }
 }
 
 impl Plugin for DmRepeat {
   // Tell the framework which ports this plugin has.
   type Ports = Ports;
 
-  // We don't need any special host features; We can leave them out.
-  type InitFeatures = ();
+  // The URID map is needed to read the time:Position objects from the host.
+  type InitFeatures = Features<'static>;
   type AudioFeatures = ();
 
   // Create a new instance of the plugin; Trivial in this case.
-  fn new(_plugin_info: &PluginInfo, _features: &mut ()) -> Option<Self> {
+  fn new(_plugin_info: &PluginInfo, features: &mut Features<'static>) -> Option<Self> {
     Some(Self {
-      repeat: Repeat::new(_plugin_info.sample_rate() as f32),
+      repeat: Repeat::with_max_delay_time(
+        _plugin_info.sample_rate() as f32,
+        Self::get_max_delay_time(),
+      ),
       is_active: false,
+      urids: features.map.populate_collection()?,
+      tempo: None,
+      tap_tempo: TapTempo::new(_plugin_info.sample_rate() as f32),
+      previous_freq: 0.,
+      custom_envelope: [-1.; CUSTOM_ENVELOPE_POINTS],
     })
   }
 
   // Process a chunk of audio. The audio ports are dereferenced to slices, which the plugin
   // iterates over.
-  fn run(&mut self, ports: &mut Ports, _features: &mut (), _sample_count: u32) {
-    let time = (ports.freq.get()).recip() * 1000.;
-    let repeats = ports.repeats.get() as usize;
-    let feedback = ports.feedback.get() * 0.01;
-    let skew = ports.skew.get() * 0.01;
-    let limiter = ports.limiter.get() == 1.;
+  fn run(&mut self, ports: &mut Ports, _features: &mut (), sample_count: u32) {
+    self.update_tempo(ports);
+    self.update_tap_tempo(ports, sample_count);
+    self.update_custom_envelope(ports);
+    let params = self.get_params(ports);
 
     if !self.is_active {
-      self.repeat.initialize_params(time, repeats, feedback, skew);
+      self.repeat.initialize_params(&params);
       self.is_active = true;
     }
 
-    for (input, output) in ports.input.iter().zip(ports.output.iter()) {
-      let repeat_output = self
+    // The ports can share a buffer, so the samples are processed in chunks that are copied in and out of the ports.
+    let mut left = [0.; BLOCK_SIZE];
+    let mut right = [0.; BLOCK_SIZE];
+    for ((input, output_left), output_right) in ports
+      .input
+      .chunks(BLOCK_SIZE)
+      .zip(ports.output.chunks(BLOCK_SIZE))
+      .zip(ports.output_right.chunks(BLOCK_SIZE))
+    {
+      let length = input.len();
+      for ((input, left), right) in input.iter().zip(left.iter_mut()).zip(right.iter_mut()) {
+        *left = input.get();
+        *right = input.get();
+      }
+      self
         .repeat
-        .process(input.get(), time, repeats, feedback, skew, limiter);
-      output.set(repeat_output);
+        .process_block_stereo_in_place((&mut left[..length], &mut right[..length]), &params);
+      for (output, sample) in output_left.iter().zip(left) {
+        output.set(sample);
+      }
+      for (output, sample) in output_right.iter().zip(right) {
+        output.set(sample);
+      }
     }
   }
 }
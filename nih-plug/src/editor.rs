@@ -5,29 +5,41 @@ use param_knob::{ParamKnob, ParamKnobSize};
 mod param_checkbox;
 use nih_plug::params::Param;
 use param_checkbox::ParamCheckbox;
+#[path = "./editor/components/param_button.rs"]
+mod param_button;
+use param_button::ParamButton;
+#[path = "./editor/components/tap_display.rs"]
+mod tap_display;
+use tap_display::TapDisplay;
 mod ui_data;
 use crate::repeat_parameters::RepeatParameters;
-use nih_plug::prelude::Editor;
+use nih_plug::prelude::{AtomicF32, Editor};
 use nih_plug_vizia::vizia::{
+  binding::LensExt,
   model::Model,
   modifiers::{LayoutModifiers, StyleModifiers, TextModifiers},
-  prelude::Units::{Pixels, Stretch},
+  prelude::Units::{Auto, Pixels, Stretch},
   style::FontWeightKeyword,
   views::{HStack, Label, VStack},
 };
 use nih_plug_vizia::{create_vizia_editor, vizia_assets, ViziaState, ViziaTheming};
-use std::sync::Arc;
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
 use ui_data::{ParamChangeEvent, UiData};
 
 const STYLE: &str = include_str!("./editor/style.css");
 
 // Makes sense to also define this here, makes it a bit easier to keep track of
 pub(crate) fn default_state() -> Arc<ViziaState> {
-  ViziaState::new(|| (400, 200))
+  ViziaState::new(|| (400, 1052))
 }
 
 pub(crate) fn create(
   params: Arc<RepeatParameters>,
+  tapped_tempo: Arc<AtomicF32>,
+  custom_shapes_have_changed: Arc<AtomicBool>,
   editor_state: Arc<ViziaState>,
 ) -> Option<Box<dyn Editor>> {
   create_vizia_editor(
@@ -41,11 +53,17 @@ pub(crate) fn create(
 
       UiData {
         params: params.clone(),
+        tapped_tempo: tapped_tempo.clone(),
+        custom_shapes_have_changed: custom_shapes_have_changed.clone(),
         gui_context: gui_context.clone(),
       }
       .build(cx);
 
       VStack::new(cx, |cx| {
+        TapDisplay::new(cx, UiData::params)
+          .width(Stretch(1.0))
+          .height(Pixels(96.0));
+
         HStack::new(cx, |cx| {
           ParamKnob::new(
             cx,
@@ -101,6 +119,363 @@ pub(crate) fn create(
         .child_space(Stretch(1.0))
         .col_between(Pixels(8.0));
 
+        HStack::new(cx, |cx| {
+          ParamKnob::new(
+            cx,
+            params.spread.name(),
+            UiData::params,
+            params.spread.as_ptr(),
+            |params| &params.spread,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.spread_mode.name(),
+            UiData::params,
+            params.spread_mode.as_ptr(),
+            |params| &params.spread_mode,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.mix.name(),
+            UiData::params,
+            params.mix.as_ptr(),
+            |params| &params.mix,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.dry.name(),
+            UiData::params,
+            params.dry.as_ptr(),
+            |params| &params.dry,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.wet.name(),
+            UiData::params,
+            params.wet.as_ptr(),
+            |params| &params.wet,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+        })
+        .child_space(Stretch(1.0))
+        .col_between(Pixels(8.0));
+
+        HStack::new(cx, |cx| {
+          ParamKnob::new(
+            cx,
+            params.pattern.name(),
+            UiData::params,
+            params.pattern.as_ptr(),
+            |params| &params.pattern,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.pattern_amount.name(),
+            UiData::params,
+            params.pattern_amount.as_ptr(),
+            |params| &params.pattern_amount,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.euclidean_steps.name(),
+            UiData::params,
+            params.euclidean_steps.as_ptr(),
+            |params| &params.euclidean_steps,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.pattern_seed.name(),
+            UiData::params,
+            params.pattern_seed.as_ptr(),
+            |params| &params.pattern_seed,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+        })
+        .child_space(Stretch(1.0))
+        .col_between(Pixels(8.0));
+
+        HStack::new(cx, |cx| {
+          ParamKnob::new(
+            cx,
+            params.tone.name(),
+            UiData::params,
+            params.tone.as_ptr(),
+            |params| &params.tone,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.tone_skew.name(),
+            UiData::params,
+            params.tone_skew.as_ptr(),
+            |params| &params.tone_skew,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.pitch.name(),
+            UiData::params,
+            params.pitch.as_ptr(),
+            |params| &params.pitch,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.reverse_mode.name(),
+            UiData::params,
+            params.reverse_mode.as_ptr(),
+            |params| &params.reverse_mode,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+        })
+        .child_space(Stretch(1.0))
+        .col_between(Pixels(8.0));
+
+        HStack::new(cx, |cx| {
+          ParamKnob::new(
+            cx,
+            params.lfo_shape.name(),
+            UiData::params,
+            params.lfo_shape.as_ptr(),
+            |params| &params.lfo_shape,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.lfo_rate.name(),
+            UiData::params,
+            params.lfo_rate.as_ptr(),
+            |params| &params.lfo_rate,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.lfo_depth.name(),
+            UiData::params,
+            params.lfo_depth.as_ptr(),
+            |params| &params.lfo_depth,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.lfo_phase_offset.name(),
+            UiData::params,
+            params.lfo_phase_offset.as_ptr(),
+            |params| &params.lfo_phase_offset,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.interpolation.name(),
+            UiData::params,
+            params.interpolation.as_ptr(),
+            |params| &params.interpolation,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+        })
+        .child_space(Stretch(1.0))
+        .col_between(Pixels(8.0));
+
+        HStack::new(cx, |cx| {
+          ParamKnob::new(
+            cx,
+            params.feedback_mode.name(),
+            UiData::params,
+            params.feedback_mode.as_ptr(),
+            |params| &params.feedback_mode,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.envelope_shape.name(),
+            UiData::params,
+            params.envelope_shape.as_ptr(),
+            |params| &params.envelope_shape,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.drive.name(),
+            UiData::params,
+            params.drive.as_ptr(),
+            |params| &params.drive,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.drive_curve.name(),
+            UiData::params,
+            params.drive_curve.as_ptr(),
+            |params| &params.drive_curve,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+        })
+        .child_space(Stretch(1.0))
+        .col_between(Pixels(8.0));
+
+        HStack::new(cx, |cx| {
+          ParamKnob::new(
+            cx,
+            params.time_mode.name(),
+            UiData::params,
+            params.time_mode.as_ptr(),
+            |params| &params.time_mode,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.glide_rate.name(),
+            UiData::params,
+            params.glide_rate.as_ptr(),
+            |params| &params.glide_rate,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.crossfade_time.name(),
+            UiData::params,
+            params.crossfade_time.as_ptr(),
+            |params| &params.crossfade_time,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.crossfade_curve.name(),
+            UiData::params,
+            params.crossfade_curve.as_ptr(),
+            |params| &params.crossfade_curve,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+        })
+        .child_space(Stretch(1.0))
+        .col_between(Pixels(8.0));
+
+        HStack::new(cx, |cx| {
+          ParamCheckbox::new(
+            cx,
+            params.sync.name(),
+            UiData::params,
+            params.sync.as_ptr(),
+            |params| &params.sync,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+          )
+          .top(Pixels(12.0));
+
+          ParamKnob::new(
+            cx,
+            params.division.name(),
+            UiData::params,
+            params.division.as_ptr(),
+            |params| &params.division,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          ParamKnob::new(
+            cx,
+            params.division_type.name(),
+            UiData::params,
+            params.division_type.as_ptr(),
+            |params| &params.division_type,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            ParamKnobSize::Regular,
+          );
+
+          VStack::new(cx, |cx| {
+            ParamButton::new(
+              cx,
+              params.tap.name(),
+              UiData::params,
+              params.tap.as_ptr(),
+              |params| &params.tap,
+              |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+            );
+
+            Label::new(
+              cx,
+              UiData::tapped_tempo.map(|tapped_tempo| {
+                let tempo = tapped_tempo.load(Ordering::Relaxed);
+                if tempo > 0. {
+                  format!("{:.1} BPM", tempo)
+                } else {
+                  String::from("-")
+                }
+              }),
+            )
+            .font_size(12.0);
+          })
+          .size(Auto)
+          .child_left(Stretch(1.0))
+          .child_right(Stretch(1.0))
+          .row_between(Pixels(4.0))
+          .top(Pixels(12.0));
+
+          ParamCheckbox::new(
+            cx,
+            params.freeze.name(),
+            UiData::params,
+            params.freeze.as_ptr(),
+            |params| &params.freeze,
+            |param_ptr, val| ParamChangeEvent::SetParam(param_ptr, val),
+          )
+          .top(Pixels(12.0));
+        })
+        .child_space(Stretch(1.0))
+        .col_between(Pixels(8.0));
+
         Label::new(cx, "dm-Repeat")
           .font_size(22.0)
           .font_weight(FontWeightKeyword::Bold)
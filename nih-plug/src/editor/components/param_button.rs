@@ -0,0 +1,60 @@
+use nih_plug::prelude::{Param, ParamPtr};
+use nih_plug_vizia::vizia::{
+  binding::Lens,
+  layout::Units::Auto,
+  modifiers::TextModifiers,
+  prelude::{
+    ActionModifiers, Context, EmitContext, LayoutModifiers, LensExt, StyleModifiers,
+    Units::{Pixels, Stretch},
+  },
+  style::FontWeightKeyword,
+  view::Handle,
+  views::{Element, Label, VStack},
+};
+use std::any::Any;
+
+/// A momentary button. The parameter is switched on for as long as the button is held down.
+pub struct ParamButton {}
+
+impl ParamButton {
+  pub fn new<'a, L, P, F, M, C>(
+    cx: &'a mut Context,
+    name: &'a str,
+    lens: L,
+    param_ptr: ParamPtr,
+    params_to_param: F,
+    on_change: C,
+  ) -> Handle<'a, VStack>
+  where
+    L: 'static + Lens + Copy + Send + Sync,
+    <L as Lens>::Source: 'static,
+    P: Param<Plain = bool>,
+    F: 'static + Fn(&<L as Lens>::Target) -> &P + Copy + Send + Sync,
+    M: Any + Send,
+    C: 'static + Fn(ParamPtr, f32) -> M + Copy + Send + Sync,
+  {
+    VStack::new(cx, |cx| {
+      Label::new(cx, name)
+        .font_size(13.0)
+        .font_weight(FontWeightKeyword::SemiBold)
+        .text_wrap(true)
+        .child_space(Stretch(1.0));
+
+      Element::new(cx)
+        .class("param-button")
+        .checked(
+          lens.map(move |p: &<L as Lens>::Target| params_to_param(p).modulated_plain_value()),
+        )
+        .on_mouse_down(move |cx, _| {
+          cx.emit(on_change(param_ptr, 1.));
+        })
+        .on_mouse_up(move |cx, _| {
+          cx.emit(on_change(param_ptr, 0.));
+        });
+    })
+    .size(Auto)
+    .child_left(Stretch(1.0))
+    .child_right(Stretch(1.0))
+    .row_between(Pixels(8.0))
+  }
+}
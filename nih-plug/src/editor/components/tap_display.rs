@@ -0,0 +1,256 @@
+use super::ui_data::CustomShapeEvent;
+use crate::repeat_parameters::{EnvelopeShape, Pattern, RepeatParameters};
+use nih_plug_vizia::{
+  vizia::{prelude::*, vg},
+  widgets::RawParamEvent,
+};
+use repeat::{CustomTap, Params, Repeat, Tap, MAX_REPEATS};
+use std::sync::Arc;
+
+const PADDING: f32 = 8.;
+const BAR_WIDTH: f32 = 4.;
+/// The horizontal distance in pixels within which a click grabs a custom tap instead of adding one.
+const GRAB_DISTANCE: f32 = 8.;
+/// The custom taps are shown over a fixed range of repeat times, so they don't move while they're dragged.
+const CUSTOM_TAP_TIME_RANGE: f32 = 8.;
+
+/// Draws every repeat as a bar at its time position with its gain as height.
+/// When the custom pattern is selected, taps are added and dragged with the left mouse button and removed with the right
+/// one. Otherwise, when the custom envelope is selected, its points can be drawn with the mouse.
+pub struct TapDisplay<L: Lens<Target = Arc<RepeatParameters>>> {
+  params: L,
+  is_drawing: bool,
+  dragged_tap: Option<usize>,
+}
+
+impl<L> TapDisplay<L>
+where
+  L: Lens<Target = Arc<RepeatParameters>>,
+{
+  pub fn new(cx: &mut Context, params: L) -> Handle<Self> {
+    Self {
+      params,
+      is_drawing: false,
+      dragged_tap: None,
+    }
+    .build(cx, |_| {})
+  }
+
+  /// Only the parameters that shape the taps are used. The time is left at its default, because the taps are scaled
+  /// to fit the display anyway.
+  fn get_params(params: &RepeatParameters) -> Params {
+    Params {
+      repeats: params.repeats.value() as usize,
+      feedback: params.feedback.value(),
+      feedback_mode: params.feedback_mode.value().into(),
+      envelope_shape: params.envelope_shape.value().into(),
+      skew: params.skew.value(),
+      pattern: params.pattern.value().into(),
+      pattern_amount: params.pattern_amount.value(),
+      euclidean_steps: params.euclidean_steps.value() as usize,
+      pattern_seed: params.pattern_seed.value() as u32,
+      ..Default::default()
+    }
+  }
+
+  fn is_custom_pattern(params: &RepeatParameters) -> bool {
+    params.pattern.value() == Pattern::Custom
+  }
+
+  fn is_custom_envelope(params: &RepeatParameters) -> bool {
+    !Self::is_custom_pattern(params) && params.envelope_shape.value() == EnvelopeShape::Custom
+  }
+
+  /// Converts the mouse position to the time and gain of a custom tap.
+  fn get_custom_tap(cx: &EventContext, x: f32, y: f32) -> (f32, f32) {
+    let bounds = cx.bounds();
+    let width = bounds.w - PADDING * 2. - BAR_WIDTH;
+    let position = ((x - bounds.x - PADDING - BAR_WIDTH / 2.) / width).clamp(0., 1.);
+    let gain = ((bounds.y + bounds.h - PADDING - y) / (bounds.h - PADDING * 2.)).clamp(0., 1.);
+    (position * CUSTOM_TAP_TIME_RANGE, gain)
+  }
+
+  /// Returns the index of the custom tap that's horizontally closest to the mouse, if it's within the grab distance.
+  fn find_custom_tap(&self, cx: &EventContext, x: f32) -> Option<usize> {
+    let bounds = cx.bounds();
+    let width = bounds.w - PADDING * 2. - BAR_WIDTH;
+    let params = self.params.get(cx);
+    let custom_taps = params.custom_taps.read().unwrap();
+    custom_taps
+      .iter()
+      .map(|(time, _)| {
+        (bounds.x + PADDING + BAR_WIDTH / 2. + time / CUSTOM_TAP_TIME_RANGE * width - x).abs()
+      })
+      .enumerate()
+      .filter(|(_, distance)| *distance <= GRAB_DISTANCE)
+      .min_by(|(_, a), (_, b)| a.total_cmp(b))
+      .map(|(index, _)| index)
+  }
+
+  /// Grabs the custom tap under the mouse, or adds a new one when there's none.
+  fn grab_custom_tap(&mut self, cx: &mut EventContext, x: f32, y: f32) {
+    let (time, gain) = Self::get_custom_tap(cx, x, y);
+    self.dragged_tap = match self.find_custom_tap(cx, x) {
+      Some(index) => {
+        cx.emit(CustomShapeEvent::MoveTap(index, time, gain));
+        Some(index)
+      }
+      None => {
+        let taps = self.params.get(cx).custom_taps.read().unwrap().len();
+        if taps < MAX_REPEATS - 1 {
+          cx.emit(CustomShapeEvent::AddTap(time, gain));
+          Some(taps)
+        } else {
+          None
+        }
+      }
+    };
+    cx.needs_redraw();
+  }
+
+  /// Sets the point of the custom envelope that's closest to the mouse to the height of the mouse.
+  fn draw_envelope(&self, cx: &mut EventContext, x: f32, y: f32) {
+    let points = self.params.get(cx).custom_envelope.read().unwrap().len();
+    if points == 0 {
+      return;
+    }
+
+    let bounds = cx.bounds();
+    let position = ((x - bounds.x - PADDING) / (bounds.w - PADDING * 2.)).clamp(0., 1.);
+    let index = (position * (points - 1) as f32).round() as usize;
+    let gain = ((bounds.y + bounds.h - PADDING - y) / (bounds.h - PADDING * 2.)).clamp(0., 1.);
+    cx.emit(CustomShapeEvent::SetEnvelopePoint(index, gain));
+    cx.needs_redraw();
+  }
+}
+
+impl<L> View for TapDisplay<L>
+where
+  L: Lens<Target = Arc<RepeatParameters>>,
+{
+  fn element(&self) -> Option<&'static str> {
+    Some("tap-display")
+  }
+
+  fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+    event.map(|param_event, _| {
+      if let RawParamEvent::ParametersChanged = param_event {
+        cx.needs_redraw();
+      }
+    });
+
+    event.map(|window_event, meta| match window_event {
+      WindowEvent::MouseDown(MouseButton::Left) => {
+        if Self::is_custom_pattern(&self.params.get(cx)) {
+          let (x, y) = (cx.mouse().cursorx, cx.mouse().cursory);
+          cx.capture();
+          self.grab_custom_tap(cx, x, y);
+          meta.consume();
+        } else if Self::is_custom_envelope(&self.params.get(cx)) {
+          let (x, y) = (cx.mouse().cursorx, cx.mouse().cursory);
+          self.is_drawing = true;
+          cx.capture();
+          self.draw_envelope(cx, x, y);
+          meta.consume();
+        }
+      }
+      WindowEvent::MouseDown(MouseButton::Right) => {
+        if Self::is_custom_pattern(&self.params.get(cx)) {
+          if let Some(index) = self.find_custom_tap(cx, cx.mouse().cursorx) {
+            cx.emit(CustomShapeEvent::RemoveTap(index));
+            cx.needs_redraw();
+          }
+          meta.consume();
+        }
+      }
+      WindowEvent::MouseMove(x, y) => {
+        if let Some(index) = self.dragged_tap {
+          let (time, gain) = Self::get_custom_tap(cx, *x, *y);
+          cx.emit(CustomShapeEvent::MoveTap(index, time, gain));
+          cx.needs_redraw();
+        } else if self.is_drawing {
+          self.draw_envelope(cx, *x, *y);
+        }
+      }
+      WindowEvent::MouseUp(MouseButton::Left) => {
+        if self.dragged_tap.is_some() || self.is_drawing {
+          self.dragged_tap = None;
+          self.is_drawing = false;
+          cx.release();
+          meta.consume();
+        }
+      }
+      _ => {}
+    });
+  }
+
+  fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+    let bounds = cx.bounds();
+    if bounds.w == 0. || bounds.h == 0. {
+      return;
+    }
+
+    let mut background = vg::Path::new();
+    background.rounded_rect(bounds.x, bounds.y, bounds.w, bounds.h, 8.);
+    canvas.fill_path(&background, &vg::Paint::color(vg::Color::rgb(54, 54, 54)));
+
+    let params = self.params.get(cx);
+    let custom_taps: Vec<CustomTap> = params
+      .custom_taps
+      .read()
+      .unwrap()
+      .iter()
+      .map(|&(time, gain)| CustomTap { time, gain })
+      .collect();
+    let custom_envelope = params.custom_envelope.read().unwrap().clone();
+    let tap_params = Self::get_params(&params);
+    let taps: Vec<Tap> = Repeat::tap_layout(&tap_params, &custom_taps, &custom_envelope).collect();
+    let is_custom_pattern = Self::is_custom_pattern(&params);
+    let max_time = if is_custom_pattern {
+      CUSTOM_TAP_TIME_RANGE * tap_params.time
+    } else {
+      taps.iter().fold(0., |max: f32, tap| max.max(tap.time))
+    };
+    let max_gain = taps
+      .iter()
+      .fold(1., |max: f32, tap| max.max(tap.gain.abs()));
+    if max_time == 0. {
+      return;
+    }
+
+    let width = bounds.w - PADDING * 2. - BAR_WIDTH;
+    let height = bounds.h - PADDING * 2.;
+    let bottom = bounds.y + bounds.h - PADDING;
+
+    let mut bars = vg::Path::new();
+    let mut handles = vg::Path::new();
+    for tap in taps {
+      let x = bounds.x + PADDING + tap.time / max_time * width;
+      let bar_height = tap.gain.abs() / max_gain * height;
+      bars.rect(x, bottom - bar_height, BAR_WIDTH, bar_height);
+      if is_custom_pattern {
+        handles.circle(x + BAR_WIDTH / 2., bottom - bar_height, BAR_WIDTH);
+      }
+    }
+    let paint = vg::Paint::color(vg::Color::rgb(0, 165, 167));
+    canvas.fill_path(&bars, &paint);
+    canvas.fill_path(&handles, &paint);
+
+    if Self::is_custom_envelope(&params) && custom_envelope.len() > 1 {
+      let mut envelope = vg::Path::new();
+      let spacing = (bounds.w - PADDING * 2.) / (custom_envelope.len() - 1) as f32;
+      for (index, gain) in custom_envelope.iter().enumerate() {
+        let x = bounds.x + PADDING + index as f32 * spacing;
+        let y = bottom - gain * height;
+        if index == 0 {
+          envelope.move_to(x, y);
+        } else {
+          envelope.line_to(x, y);
+        }
+      }
+      let mut paint = vg::Paint::color(vg::Color::rgb(225, 217, 209));
+      paint.set_line_width(2.);
+      canvas.stroke_path(&envelope, &paint);
+    }
+  }
+}
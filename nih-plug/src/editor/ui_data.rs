@@ -1,14 +1,32 @@
 use crate::repeat_parameters::RepeatParameters;
-use nih_plug::{params::internals::ParamPtr, prelude::GuiContext};
+use nih_plug::{
+  params::internals::ParamPtr,
+  prelude::{AtomicF32, GuiContext},
+};
 use nih_plug_vizia::vizia::prelude::*;
-use std::sync::Arc;
+use repeat::MAX_REPEATS;
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
 pub enum ParamChangeEvent {
   SetParam(ParamPtr, f32),
 }
 
+/// Edits the custom shapes, which are saved with the plugin state instead of as parameters.
+pub enum CustomShapeEvent {
+  SetEnvelopePoint(usize, f32),
+  /// Adds a custom tap with the given time and gain.
+  AddTap(f32, f32),
+  MoveTap(usize, f32, f32),
+  RemoveTap(usize),
+}
+
 #[derive(Lens)]
 pub struct UiData {
   pub params: Arc<RepeatParameters>,
+  pub tapped_tempo: Arc<AtomicF32>,
+  pub custom_shapes_have_changed: Arc<AtomicBool>,
   pub gui_context: Arc<dyn GuiContext>,
 }
 
@@ -23,5 +41,36 @@ impl Model for UiData {
         };
       }
     });
+
+    event.map(|custom_shape_event, _| {
+      match custom_shape_event {
+        CustomShapeEvent::SetEnvelopePoint(index, gain) => {
+          if let Some(point) = self.params.custom_envelope.write().unwrap().get_mut(*index) {
+            *point = *gain;
+          }
+        }
+        CustomShapeEvent::AddTap(time, gain) => {
+          let mut custom_taps = self.params.custom_taps.write().unwrap();
+          // The first repeat is the dry signal, so there's room for one tap less than the maximum number of repeats.
+          if custom_taps.len() < MAX_REPEATS - 1 {
+            custom_taps.push((*time, *gain));
+          }
+        }
+        CustomShapeEvent::MoveTap(index, time, gain) => {
+          if let Some(tap) = self.params.custom_taps.write().unwrap().get_mut(*index) {
+            *tap = (*time, *gain);
+          }
+        }
+        CustomShapeEvent::RemoveTap(index) => {
+          let mut custom_taps = self.params.custom_taps.write().unwrap();
+          if *index < custom_taps.len() {
+            custom_taps.remove(*index);
+          }
+        }
+      }
+      self
+        .custom_shapes_have_changed
+        .store(true, Ordering::Relaxed);
+    });
   }
 }
@@ -1,24 +1,120 @@
 use nih_plug::prelude::*;
-use repeat::Repeat;
-use std::sync::Arc;
+use repeat::{CustomTap, Params as RepeatParams, Repeat, TapTempo, MAX_REPEATS};
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
 mod repeat_parameters;
 use repeat_parameters::RepeatParameters;
 mod editor;
 
+/// The tempo that's used for synced repeats when the host doesn't provide one.
+const DEFAULT_TEMPO: f32 = 120.;
+
 struct DmRepeat {
   params: Arc<RepeatParameters>,
   repeat: Repeat,
+  tap_tempo: TapTempo,
+  /// The tapped tempo in beats per minute, shared with the editor. It's zero when no tempo has been tapped.
+  tapped_tempo: Arc<AtomicF32>,
+  /// Set by the editor when the custom taps or envelope are drawn, so they're passed to the repeat engine in the next
+  /// block.
+  custom_shapes_have_changed: Arc<AtomicBool>,
+  /// A preallocated buffer for the custom taps, so they can be passed to the repeat engine without allocating.
+  custom_taps: Vec<CustomTap>,
+  previous_freq: f32,
 }
 
 impl DmRepeat {
-  fn get_params(&self) -> (f32, usize, f32, f32, bool) {
-    (
-      self.params.freq.value().recip() * 1000.,
-      self.params.repeats.value() as usize,
-      self.params.feedback.value(),
-      self.params.skew.value(),
-      self.params.limiter.value(),
-    )
+  fn get_time(&self, tempo: Option<f64>) -> f32 {
+    if self.params.sync.value() {
+      let beat_time = 60000. / tempo.map_or(DEFAULT_TEMPO, |tempo| tempo as f32);
+      beat_time
+        * self.params.division.value().get_beats()
+        * self.params.division_type.value().get_factor()
+    } else {
+      self
+        .tap_tempo
+        .get_time()
+        .unwrap_or_else(|| self.params.freq.value().recip() * 1000.)
+    }
+  }
+
+  /// Moving the freq parameter overrides the tapped time.
+  fn update_tap_tempo(&mut self, sample_count: usize) {
+    let freq = self.params.freq.value();
+    if freq != self.previous_freq {
+      self.tap_tempo.reset();
+      self.previous_freq = freq;
+    }
+
+    self
+      .tap_tempo
+      .process(self.params.tap.value(), sample_count);
+    self
+      .tapped_tempo
+      .store(self.tap_tempo.get_tempo().unwrap_or(0.), Ordering::Relaxed);
+  }
+
+  /// Passes the custom taps and envelope to the repeat engine. When the editor is drawing them, the update is retried in
+  /// the next block.
+  fn update_custom_shapes(&mut self) {
+    match (
+      self.params.custom_taps.try_read(),
+      self.params.custom_envelope.try_read(),
+    ) {
+      (Ok(custom_taps), Ok(custom_envelope)) => {
+        self.custom_taps.clear();
+        self.custom_taps.extend(
+          custom_taps
+            .iter()
+            .take(MAX_REPEATS - 1)
+            .map(|&(time, gain)| CustomTap { time, gain }),
+        );
+        self.repeat.set_custom_taps(&self.custom_taps);
+        self.repeat.set_custom_envelope(&custom_envelope);
+      }
+      _ => self
+        .custom_shapes_have_changed
+        .store(true, Ordering::Relaxed),
+    }
+  }
+
+  fn get_params(&self, tempo: Option<f64>) -> RepeatParams {
+    RepeatParams {
+      time: self.get_time(tempo),
+      repeats: self.params.repeats.value() as usize,
+      feedback: self.params.feedback.value(),
+      feedback_mode: self.params.feedback_mode.value().into(),
+      envelope_shape: self.params.envelope_shape.value().into(),
+      skew: self.params.skew.value(),
+      pattern: self.params.pattern.value().into(),
+      pattern_amount: self.params.pattern_amount.value(),
+      euclidean_steps: self.params.euclidean_steps.value() as usize,
+      pattern_seed: self.params.pattern_seed.value() as u32,
+      tone: self.params.tone.value(),
+      tone_skew: self.params.tone_skew.value(),
+      pitch: self.params.pitch.value(),
+      reverse_mode: self.params.reverse_mode.value().into(),
+      lfo_shape: self.params.lfo_shape.value().into(),
+      lfo_rate: self.params.lfo_rate.value(),
+      lfo_depth: self.params.lfo_depth.value(),
+      lfo_phase_offset: self.params.lfo_phase_offset.value(),
+      interpolation: self.params.interpolation.value().into(),
+      time_mode: self.params.time_mode.value().into(),
+      glide_rate: self.params.glide_rate.value(),
+      crossfade_time: self.params.crossfade_time.value(),
+      crossfade_curve: self.params.crossfade_curve.value().into(),
+      drive: self.params.drive.value(),
+      drive_curve: self.params.drive_curve.value().into(),
+      spread: self.params.spread.value(),
+      spread_mode: self.params.spread_mode.value().into(),
+      limiter: self.params.limiter.value(),
+      freeze: self.params.freeze.value(),
+      mix: self.params.mix.value(),
+      dry: self.params.dry.value(),
+      wet: self.params.wet.value(),
+    }
   }
 }
 
@@ -28,6 +124,11 @@ impl Default for DmRepeat {
     Self {
       params: params.clone(),
       repeat: Repeat::new(44100.),
+      tap_tempo: TapTempo::new(44100.),
+      tapped_tempo: Arc::new(AtomicF32::new(0.)),
+      custom_shapes_have_changed: Arc::new(AtomicBool::new(false)),
+      custom_taps: Vec::with_capacity(MAX_REPEATS - 1),
+      previous_freq: params.freq.value(),
     }
   }
 }
@@ -65,7 +166,12 @@ impl Plugin for DmRepeat {
   }
 
   fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
-    editor::create(self.params.clone(), self.params.editor_state.clone())
+    editor::create(
+      self.params.clone(),
+      self.tapped_tempo.clone(),
+      self.custom_shapes_have_changed.clone(),
+      self.params.editor_state.clone(),
+    )
   }
 
   fn initialize(
@@ -75,8 +181,10 @@ impl Plugin for DmRepeat {
     _context: &mut impl InitContext<Self>,
   ) -> bool {
     self.repeat = Repeat::new(buffer_config.sample_rate);
-    let (time, repeats, feedback, skew, _) = self.get_params();
-    self.repeat.initialize_params(time, repeats, feedback, skew);
+    self.tap_tempo = TapTempo::new(buffer_config.sample_rate);
+    // This also runs after the state is restored, so the saved custom shapes are used.
+    self.update_custom_shapes();
+    self.repeat.initialize_params(&self.get_params(None));
     true
   }
 
@@ -84,32 +192,24 @@ impl Plugin for DmRepeat {
     &mut self,
     buffer: &mut Buffer,
     _aux: &mut AuxiliaryBuffers,
-    _context: &mut impl ProcessContext<Self>,
+    context: &mut impl ProcessContext<Self>,
   ) -> ProcessStatus {
-    let (time, repeats, feedback, skew, limiter) = self.get_params();
-
-    buffer.iter_samples().for_each(|mut channel_samples| {
-      if channel_samples.len() == 2 {
-        let channel_iterator = &mut channel_samples.iter_mut();
-        let left_channel = channel_iterator.next().unwrap();
-        let right_channel = channel_iterator.next().unwrap();
-        let repeat_output = self.repeat.process(
-          (*left_channel + *right_channel) * 0.5,
-          time,
-          repeats,
-          feedback,
-          skew,
-          limiter,
-        );
-        *left_channel = repeat_output;
-        *right_channel = repeat_output;
-      } else {
-        let sample = channel_samples.iter_mut().next().unwrap();
-        *sample = self
-          .repeat
-          .process(*sample, time, repeats, feedback, skew, limiter);
-      };
-    });
+    self.update_tap_tempo(buffer.samples());
+    if self
+      .custom_shapes_have_changed
+      .swap(false, Ordering::Relaxed)
+    {
+      self.update_custom_shapes();
+    }
+    let params = self.get_params(context.transport().tempo);
+
+    match buffer.as_slice() {
+      [left, right] => self
+        .repeat
+        .process_block_stereo_in_place((left, right), &params),
+      [mono] => self.repeat.process_block_in_place(mono, &params),
+      _ => (),
+    }
     ProcessStatus::Normal
   }
 
@@ -126,6 +226,7 @@ impl ClapPlugin for DmRepeat {
   const CLAP_FEATURES: &'static [ClapFeature] = &[
     ClapFeature::AudioEffect,
     ClapFeature::Mono,
+    ClapFeature::Stereo,
     ClapFeature::Delay,
   ];
 }
@@ -136,6 +237,7 @@ impl Vst3Plugin for DmRepeat {
     Vst3SubCategory::Fx,
     Vst3SubCategory::Delay,
     Vst3SubCategory::Mono,
+    Vst3SubCategory::Stereo,
   ];
 }
 
@@ -1,16 +1,258 @@
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use nih_plug::{
-  formatters::{s2v_f32_percentage, v2s_f32_percentage},
-  params::BoolParam,
-  prelude::{FloatParam, FloatRange, IntParam, IntRange, Params},
+  formatters::{s2v_f32_gain_to_db, s2v_f32_percentage, v2s_f32_gain_to_db, v2s_f32_percentage},
+  params::{BoolParam, EnumParam},
+  prelude::{Enum, FloatParam, FloatRange, IntParam, IntRange, Params},
+  util::db_to_gain,
 };
 mod custom_formatters;
 use custom_formatters::v2s_f32_digits;
 use nih_plug_vizia::ViziaState;
+use repeat::CUSTOM_ENVELOPE_POINTS;
 
 use crate::editor;
 
+#[derive(Enum, PartialEq)]
+pub enum SpreadMode {
+  #[name = "Ping-pong"]
+  PingPong,
+  Linear,
+  Random,
+  #[name = "Center-out"]
+  CenterOut,
+}
+
+impl From<SpreadMode> for repeat::SpreadMode {
+  fn from(spread_mode: SpreadMode) -> Self {
+    match spread_mode {
+      SpreadMode::PingPong => repeat::SpreadMode::PingPong,
+      SpreadMode::Linear => repeat::SpreadMode::Linear,
+      SpreadMode::Random => repeat::SpreadMode::Random,
+      SpreadMode::CenterOut => repeat::SpreadMode::CenterOut,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum Pattern {
+  Skew,
+  Euclidean,
+  Swing,
+  Golden,
+  Random,
+  Custom,
+}
+
+impl From<Pattern> for repeat::Pattern {
+  fn from(pattern: Pattern) -> Self {
+    match pattern {
+      Pattern::Skew => repeat::Pattern::Skew,
+      Pattern::Euclidean => repeat::Pattern::Euclidean,
+      Pattern::Swing => repeat::Pattern::Swing,
+      Pattern::Golden => repeat::Pattern::Golden,
+      Pattern::Random => repeat::Pattern::Random,
+      Pattern::Custom => repeat::Pattern::Custom,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum FeedbackMode {
+  Simulated,
+  Recirculating,
+}
+
+impl From<FeedbackMode> for repeat::FeedbackMode {
+  fn from(feedback_mode: FeedbackMode) -> Self {
+    match feedback_mode {
+      FeedbackMode::Simulated => repeat::FeedbackMode::Simulated,
+      FeedbackMode::Recirculating => repeat::FeedbackMode::Recirculating,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum EnvelopeShape {
+  Exponential,
+  Linear,
+  Bell,
+  Gate,
+  Random,
+  Custom,
+}
+
+impl From<EnvelopeShape> for repeat::EnvelopeShape {
+  fn from(envelope_shape: EnvelopeShape) -> Self {
+    match envelope_shape {
+      EnvelopeShape::Exponential => repeat::EnvelopeShape::Exponential,
+      EnvelopeShape::Linear => repeat::EnvelopeShape::Linear,
+      EnvelopeShape::Bell => repeat::EnvelopeShape::Bell,
+      EnvelopeShape::Gate => repeat::EnvelopeShape::Gate,
+      EnvelopeShape::Random => repeat::EnvelopeShape::Random,
+      EnvelopeShape::Custom => repeat::EnvelopeShape::Custom,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum DriveCurve {
+  Tanh,
+  Atan,
+  #[name = "Soft Clip"]
+  SoftClip,
+  Tube,
+}
+
+impl From<DriveCurve> for repeat::DriveCurve {
+  fn from(drive_curve: DriveCurve) -> Self {
+    match drive_curve {
+      DriveCurve::Tanh => repeat::DriveCurve::Tanh,
+      DriveCurve::Atan => repeat::DriveCurve::Atan,
+      DriveCurve::SoftClip => repeat::DriveCurve::SoftClip,
+      DriveCurve::Tube => repeat::DriveCurve::Tube,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum ReverseMode {
+  Off,
+  All,
+  Alternate,
+}
+
+impl From<ReverseMode> for repeat::ReverseMode {
+  fn from(reverse_mode: ReverseMode) -> Self {
+    match reverse_mode {
+      ReverseMode::Off => repeat::ReverseMode::Off,
+      ReverseMode::All => repeat::ReverseMode::All,
+      ReverseMode::Alternate => repeat::ReverseMode::Alternate,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum LfoShape {
+  Sine,
+  Triangle,
+  Random,
+}
+
+impl From<LfoShape> for repeat::LfoShape {
+  fn from(lfo_shape: LfoShape) -> Self {
+    match lfo_shape {
+      LfoShape::Sine => repeat::LfoShape::Sine,
+      LfoShape::Triangle => repeat::LfoShape::Triangle,
+      LfoShape::Random => repeat::LfoShape::Random,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum Interpolation {
+  Step,
+  Linear,
+  Cosine,
+  Cubic,
+  Spline,
+}
+
+impl From<Interpolation> for repeat::Interpolation {
+  fn from(interpolation: Interpolation) -> Self {
+    match interpolation {
+      Interpolation::Step => repeat::Interpolation::Step,
+      Interpolation::Linear => repeat::Interpolation::Linear,
+      Interpolation::Cosine => repeat::Interpolation::Cosine,
+      Interpolation::Cubic => repeat::Interpolation::Cubic,
+      Interpolation::Spline => repeat::Interpolation::Spline,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum TimeMode {
+  Crossfade,
+  Tape,
+}
+
+impl From<TimeMode> for repeat::TimeMode {
+  fn from(time_mode: TimeMode) -> Self {
+    match time_mode {
+      TimeMode::Crossfade => repeat::TimeMode::Crossfade,
+      TimeMode::Tape => repeat::TimeMode::Tape,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum CrossfadeCurve {
+  Linear,
+  Smooth,
+  #[name = "Equal Power"]
+  EqualPower,
+}
+
+impl From<CrossfadeCurve> for repeat::CrossfadeCurve {
+  fn from(crossfade_curve: CrossfadeCurve) -> Self {
+    match crossfade_curve {
+      CrossfadeCurve::Linear => repeat::CrossfadeCurve::Linear,
+      CrossfadeCurve::Smooth => repeat::CrossfadeCurve::Smooth,
+      CrossfadeCurve::EqualPower => repeat::CrossfadeCurve::EqualPower,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum Division {
+  #[name = "1/1"]
+  Whole,
+  #[name = "1/2"]
+  Half,
+  #[name = "1/4"]
+  Quarter,
+  #[name = "1/8"]
+  Eighth,
+  #[name = "1/16"]
+  Sixteenth,
+  #[name = "1/32"]
+  ThirtySecond,
+  #[name = "1/64"]
+  SixtyFourth,
+}
+
+impl Division {
+  /// Returns the length of the note division in quarter notes.
+  pub fn get_beats(&self) -> f32 {
+    match self {
+      Division::Whole => 4.,
+      Division::Half => 2.,
+      Division::Quarter => 1.,
+      Division::Eighth => 0.5,
+      Division::Sixteenth => 0.25,
+      Division::ThirtySecond => 0.125,
+      Division::SixtyFourth => 0.0625,
+    }
+  }
+}
+
+#[derive(Enum, PartialEq)]
+pub enum DivisionType {
+  Straight,
+  Dotted,
+  Triplet,
+}
+
+impl DivisionType {
+  pub fn get_factor(&self) -> f32 {
+    match self {
+      DivisionType::Straight => 1.,
+      DivisionType::Dotted => 1.5,
+      DivisionType::Triplet => 2. / 3.,
+    }
+  }
+}
+
 #[derive(Params)]
 pub struct RepeatParameters {
   /// The editor state, saved together with the parameter state so the custom scaling can be
@@ -18,20 +260,122 @@ pub struct RepeatParameters {
   #[persist = "editor-state"]
   pub editor_state: Arc<ViziaState>,
 
+  /// The gains of the custom envelope, which are drawn in the tap display.
+  #[persist = "custom-envelope"]
+  pub custom_envelope: RwLock<Vec<f32>>,
+
+  /// The time and gain of every tap of the custom pattern, which are drawn in the tap display. The time is a multiple
+  /// of the repeat time.
+  #[persist = "custom-taps"]
+  pub custom_taps: RwLock<Vec<(f32, f32)>>,
+
+  #[id = "sync"]
+  pub sync: BoolParam,
+
   #[id = "freq"]
   pub freq: FloatParam,
 
+  #[id = "division"]
+  pub division: EnumParam<Division>,
+
+  #[id = "division_type"]
+  pub division_type: EnumParam<DivisionType>,
+
+  #[id = "tap"]
+  pub tap: BoolParam,
+
   #[id = "repeats"]
   pub repeats: IntParam,
 
   #[id = "feedback"]
   pub feedback: FloatParam,
 
+  #[id = "feedback_mode"]
+  pub feedback_mode: EnumParam<FeedbackMode>,
+
+  #[id = "envelope_shape"]
+  pub envelope_shape: EnumParam<EnvelopeShape>,
+
   #[id = "skew"]
   pub skew: FloatParam,
 
+  #[id = "tone"]
+  pub tone: FloatParam,
+
+  #[id = "tone_skew"]
+  pub tone_skew: FloatParam,
+
+  #[id = "pitch"]
+  pub pitch: FloatParam,
+
+  #[id = "reverse_mode"]
+  pub reverse_mode: EnumParam<ReverseMode>,
+
+  #[id = "lfo_shape"]
+  pub lfo_shape: EnumParam<LfoShape>,
+
+  #[id = "lfo_rate"]
+  pub lfo_rate: FloatParam,
+
+  #[id = "lfo_depth"]
+  pub lfo_depth: FloatParam,
+
+  #[id = "lfo_phase_offset"]
+  pub lfo_phase_offset: FloatParam,
+
+  #[id = "interpolation"]
+  pub interpolation: EnumParam<Interpolation>,
+
+  #[id = "time_mode"]
+  pub time_mode: EnumParam<TimeMode>,
+
+  #[id = "glide_rate"]
+  pub glide_rate: FloatParam,
+
+  #[id = "crossfade_time"]
+  pub crossfade_time: FloatParam,
+
+  #[id = "crossfade_curve"]
+  pub crossfade_curve: EnumParam<CrossfadeCurve>,
+
+  #[id = "drive"]
+  pub drive: FloatParam,
+
+  #[id = "drive_curve"]
+  pub drive_curve: EnumParam<DriveCurve>,
+
+  #[id = "pattern"]
+  pub pattern: EnumParam<Pattern>,
+
+  #[id = "pattern_amount"]
+  pub pattern_amount: FloatParam,
+
+  #[id = "euclidean_steps"]
+  pub euclidean_steps: IntParam,
+
+  #[id = "pattern_seed"]
+  pub pattern_seed: IntParam,
+
+  #[id = "spread"]
+  pub spread: FloatParam,
+
+  #[id = "spread_mode"]
+  pub spread_mode: EnumParam<SpreadMode>,
+
   #[id = "limiter"]
   pub limiter: BoolParam,
+
+  #[id = "freeze"]
+  pub freeze: BoolParam,
+
+  #[id = "mix"]
+  pub mix: FloatParam,
+
+  #[id = "dry"]
+  pub dry: FloatParam,
+
+  #[id = "wet"]
+  pub wet: FloatParam,
 }
 
 impl Default for RepeatParameters {
@@ -39,6 +383,16 @@ impl Default for RepeatParameters {
     Self {
       editor_state: editor::default_state(),
 
+      custom_envelope: RwLock::new(
+        (0..CUSTOM_ENVELOPE_POINTS)
+          .map(|index| 1. - index as f32 / CUSTOM_ENVELOPE_POINTS as f32)
+          .collect(),
+      ),
+
+      custom_taps: RwLock::new(vec![(1., 1.), (1.5, 0.5), (3., 0.75)]),
+
+      sync: BoolParam::new("Sync", false),
+
       freq: FloatParam::new(
         "Freq",
         4.,
@@ -51,7 +405,13 @@ impl Default for RepeatParameters {
       .with_unit(" Hz")
       .with_value_to_string(v2s_f32_digits(2)),
 
-      repeats: IntParam::new("Repeats", 4, IntRange::Linear { min: 1, max: 24 }).with_unit(" x"),
+      division: EnumParam::new("Division", Division::Quarter),
+
+      division_type: EnumParam::new("Type", DivisionType::Straight),
+
+      tap: BoolParam::new("Tap", false).non_automatable().hide(),
+
+      repeats: IntParam::new("Repeats", 4, IntRange::Linear { min: 1, max: 64 }).with_unit(" x"),
 
       feedback: FloatParam::new(
         "Feedback",
@@ -65,12 +425,158 @@ impl Default for RepeatParameters {
       .with_value_to_string(v2s_f32_percentage(2))
       .with_string_to_value(s2v_f32_percentage()),
 
+      feedback_mode: EnumParam::new("Feedback Mode", FeedbackMode::Simulated),
+
+      envelope_shape: EnumParam::new("Envelope", EnvelopeShape::Exponential),
+
       skew: FloatParam::new("Skew", 0., FloatRange::Linear { min: -1., max: 1. })
         .with_unit(" %")
         .with_value_to_string(v2s_f32_percentage(2))
         .with_string_to_value(s2v_f32_percentage()),
 
+      tone: FloatParam::new("Tone", 0., FloatRange::Linear { min: -1., max: 1. })
+        .with_unit(" %")
+        .with_value_to_string(v2s_f32_percentage(2))
+        .with_string_to_value(s2v_f32_percentage()),
+
+      tone_skew: FloatParam::new("Tone Skew", 0., FloatRange::Linear { min: -1., max: 1. })
+        .with_unit(" %")
+        .with_value_to_string(v2s_f32_percentage(2))
+        .with_string_to_value(s2v_f32_percentage()),
+
+      pitch: FloatParam::new(
+        "Pitch",
+        0.,
+        FloatRange::Linear {
+          min: -12.,
+          max: 12.,
+        },
+      )
+      .with_unit(" st")
+      .with_value_to_string(v2s_f32_digits(2)),
+
+      reverse_mode: EnumParam::new("Reverse", ReverseMode::Off),
+
+      lfo_shape: EnumParam::new("LFO Shape", LfoShape::Sine),
+
+      lfo_rate: FloatParam::new(
+        "LFO Rate",
+        1.,
+        FloatRange::Skewed {
+          min: 0.05,
+          max: 10.,
+          factor: 0.3,
+        },
+      )
+      .with_unit(" Hz")
+      .with_value_to_string(v2s_f32_digits(2)),
+
+      lfo_depth: FloatParam::new(
+        "LFO Depth",
+        0.,
+        FloatRange::Skewed {
+          min: 0.,
+          max: 10.,
+          factor: 0.5,
+        },
+      )
+      .with_unit(" ms")
+      .with_value_to_string(v2s_f32_digits(2)),
+
+      lfo_phase_offset: FloatParam::new("LFO Phase", 0., FloatRange::Linear { min: 0., max: 1. })
+        .with_unit(" %")
+        .with_value_to_string(v2s_f32_percentage(2))
+        .with_string_to_value(s2v_f32_percentage()),
+
+      interpolation: EnumParam::new("Quality", Interpolation::Step),
+
+      time_mode: EnumParam::new("Time Mode", TimeMode::Crossfade),
+
+      glide_rate: FloatParam::new(
+        "Glide",
+        200.,
+        FloatRange::Skewed {
+          min: 10.,
+          max: 2000.,
+          factor: 0.3,
+        },
+      )
+      .with_unit(" ms/s")
+      .with_value_to_string(v2s_f32_digits(2)),
+
+      crossfade_time: FloatParam::new(
+        "Fade Time",
+        200.,
+        FloatRange::Skewed {
+          min: 10.,
+          max: 2000.,
+          factor: 0.3,
+        },
+      )
+      .with_unit(" ms")
+      .with_value_to_string(v2s_f32_digits(2)),
+
+      crossfade_curve: EnumParam::new("Fade Curve", CrossfadeCurve::Smooth),
+
+      drive: FloatParam::new("Drive", 0., FloatRange::Linear { min: 0., max: 1. })
+        .with_unit(" %")
+        .with_value_to_string(v2s_f32_percentage(2))
+        .with_string_to_value(s2v_f32_percentage()),
+
+      drive_curve: EnumParam::new("Curve", DriveCurve::Tanh),
+
+      pattern: EnumParam::new("Pattern", Pattern::Skew),
+
+      pattern_amount: FloatParam::new("Amount", 0., FloatRange::Linear { min: 0., max: 1. })
+        .with_unit(" %")
+        .with_value_to_string(v2s_f32_percentage(2))
+        .with_string_to_value(s2v_f32_percentage()),
+
+      euclidean_steps: IntParam::new("Steps", 8, IntRange::Linear { min: 1, max: 64 }),
+
+      pattern_seed: IntParam::new("Seed", 0, IntRange::Linear { min: 0, max: 999 }),
+
+      spread: FloatParam::new("Spread", 0., FloatRange::Linear { min: 0., max: 1. })
+        .with_unit(" %")
+        .with_value_to_string(v2s_f32_percentage(2))
+        .with_string_to_value(s2v_f32_percentage()),
+
+      spread_mode: EnumParam::new("Spread Mode", SpreadMode::PingPong),
+
       limiter: BoolParam::new("Limiter", false),
+
+      freeze: BoolParam::new("Freeze", false),
+
+      mix: FloatParam::new("Mix", 0.5, FloatRange::Linear { min: 0., max: 1. })
+        .with_unit(" %")
+        .with_value_to_string(v2s_f32_percentage(2))
+        .with_string_to_value(s2v_f32_percentage()),
+
+      dry: FloatParam::new(
+        "Dry",
+        db_to_gain(0.),
+        FloatRange::Skewed {
+          min: db_to_gain(-60.),
+          max: db_to_gain(12.),
+          factor: FloatRange::gain_skew_factor(-60., 12.),
+        },
+      )
+      .with_unit(" dB")
+      .with_value_to_string(v2s_f32_gain_to_db(2))
+      .with_string_to_value(s2v_f32_gain_to_db()),
+
+      wet: FloatParam::new(
+        "Wet",
+        db_to_gain(0.),
+        FloatRange::Skewed {
+          min: db_to_gain(-60.),
+          max: db_to_gain(12.),
+          factor: FloatRange::gain_skew_factor(-60., 12.),
+        },
+      )
+      .with_unit(" dB")
+      .with_value_to_string(v2s_f32_gain_to_db(2))
+      .with_string_to_value(s2v_f32_gain_to_db()),
     }
   }
 }
@@ -1,21 +1,132 @@
 #[path = "../src/utils.rs"]
 mod utils;
-use criterion::{criterion_group, criterion_main, Criterion};
-use repeat::Repeat;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use repeat::{Params, Repeat, TapSummation, MAX_REPEATS};
 use utils::generate_signal_stream;
 
+const BLOCK_SIZE: usize = 64;
+
 fn repeat_bench(c: &mut Criterion) {
   let mut repeat = Repeat::new(44100.);
+  let params = Params {
+    time: 100.,
+    repeats: 16,
+    feedback: 1.,
+    skew: -0.25,
+    limiter: true,
+    ..Default::default()
+  };
   let signal_stream = generate_signal_stream(44100);
+  let mut output = vec![0.; signal_stream.len()];
 
   c.bench_function("repeat", |b| {
     b.iter(|| {
-      for signal in &signal_stream {
-        repeat.process(*signal, 100., 16, 1., -0.25, true);
+      for (input, output) in signal_stream
+        .chunks(BLOCK_SIZE)
+        .zip(output.chunks_mut(BLOCK_SIZE))
+      {
+        repeat.process_block(input, output, &params);
+      }
+    })
+  });
+}
+
+fn max_repeats_bench(c: &mut Criterion) {
+  let mut repeat = Repeat::new(44100.);
+  let params = Params {
+    time: 20.,
+    repeats: MAX_REPEATS,
+    feedback: 0.9,
+    skew: -0.25,
+    limiter: true,
+    ..Default::default()
+  };
+  let signal_stream = generate_signal_stream(44100);
+  let mut output = vec![0.; signal_stream.len()];
+
+  c.bench_function("repeat_max_repeats", |b| {
+    b.iter(|| {
+      for (input, output) in signal_stream
+        .chunks(BLOCK_SIZE)
+        .zip(output.chunks_mut(BLOCK_SIZE))
+      {
+        repeat.process_block(input, output, &params);
       }
     })
   });
 }
 
-criterion_group!(benches, repeat_bench);
+fn initialize_params_bench(c: &mut Criterion) {
+  let mut repeat = Repeat::<f32>::new(44100.);
+  let params = [
+    Params {
+      time: 20.,
+      repeats: MAX_REPEATS,
+      feedback: 0.9,
+      skew: -0.25,
+      tone: -0.5,
+      ..Default::default()
+    },
+    Params {
+      time: 20.,
+      repeats: MAX_REPEATS,
+      feedback: 0.8,
+      skew: 0.25,
+      tone: 0.5,
+      ..Default::default()
+    },
+  ];
+
+  c.bench_function("initialize_params_max_repeats", |b| {
+    b.iter(|| {
+      for params in &params {
+        repeat.initialize_params(params);
+      }
+    })
+  });
+}
+
+fn tap_summation_bench(c: &mut Criterion) {
+  let signal_stream = generate_signal_stream(44100);
+  let mut output = vec![0.; signal_stream.len()];
+  let mut group = c.benchmark_group("tap_summation");
+
+  for repeats in [4, 16, 32, MAX_REPEATS] {
+    let params = Params {
+      time: 20.,
+      repeats,
+      feedback: 0.9,
+      ..Default::default()
+    };
+
+    for (name, tap_summation) in [
+      ("scalar", TapSummation::Scalar),
+      ("chunked", TapSummation::Chunked),
+    ] {
+      let mut repeat = Repeat::new(44100.);
+      repeat.set_tap_summation(tap_summation);
+      repeat.initialize_params(&params);
+
+      group.bench_function(BenchmarkId::new(name, repeats), |b| {
+        b.iter(|| {
+          for (input, output) in signal_stream
+            .chunks(BLOCK_SIZE)
+            .zip(output.chunks_mut(BLOCK_SIZE))
+          {
+            repeat.process_block(input, output, &params);
+          }
+        })
+      });
+    }
+  }
+  group.finish();
+}
+
+criterion_group!(
+  benches,
+  repeat_bench,
+  max_repeats_bench,
+  initialize_params_bench,
+  tap_summation_bench
+);
 criterion_main!(benches);
@@ -1,12 +1,26 @@
 mod utils;
-use repeat::Repeat;
+use repeat::{Params, Repeat};
 use utils::generate_signal;
 
+const BLOCK_SIZE: usize = 64;
+
 fn main() {
   let mut repeat = Repeat::new(44100.);
+  let params = Params {
+    time: 100.,
+    repeats: 16,
+    feedback: 1.,
+    skew: -0.25,
+    limiter: true,
+    ..Default::default()
+  };
+
+  let mut block = [0.; BLOCK_SIZE];
 
   loop {
-    let input = generate_signal();
-    repeat.process(input, 100., 16, 1., -0.25, true);
+    block
+      .iter_mut()
+      .for_each(|sample| *sample = generate_signal());
+    repeat.process_block_in_place(&mut block, &params);
   }
 }
@@ -0,0 +1,163 @@
+use {
+  crate::{ramp::Ramp, shared::float_ext::FloatExt},
+  core::f32::consts::FRAC_PI_2,
+};
+
+/// The number of tap layouts that can sound at once. With three voices a parameter change can start a new crossfade
+/// while the previous one is still running.
+pub const VOICES: usize = 3;
+/// The shortest crossfade time in milliseconds.
+const MIN_TIME: f32 = 1.;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum CrossfadeCurve {
+  Linear,
+  /// A sin² window. The gains add up to one like the linear curve, but the fade starts and ends gradually.
+  Smooth,
+  /// A sine and cosine window, which keeps the loudness constant when the tap layouts are uncorrelated.
+  EqualPower,
+}
+
+impl CrossfadeCurve {
+  /// Returns the gain of the voice that fades in and the gain of the voices that fade out.
+  fn get_gains(&self, x: f32) -> (f32, f32) {
+    match self {
+      CrossfadeCurve::Linear => (x, 1. - x),
+      CrossfadeCurve::Smooth => {
+        let window = x * FRAC_PI_2;
+        let fade_in = window.fast_sin();
+        let fade_in = fade_in * fade_in;
+        (fade_in, 1. - fade_in)
+      }
+      CrossfadeCurve::EqualPower => (
+        (x * FRAC_PI_2).fast_sin(),
+        ((1. - x) * FRAC_PI_2).fast_sin(),
+      ),
+    }
+  }
+}
+
+/// Crossfades between the voices of the tap layouts. A new voice fades in, while every other voice fades out from the gain
+/// it had when the crossfade started. Only voices that are silent can be started, so there are no clicks.
+pub struct Crossfade {
+  sample_rate: f32,
+  ramp: Ramp,
+  curve: CrossfadeCurve,
+  active_voice: usize,
+  start_gains: [f32; VOICES],
+  gains: [f32; VOICES],
+}
+
+impl Crossfade {
+  pub fn new(sample_rate: f32) -> Self {
+    let mut gains = [0.; VOICES];
+    gains[0] = 1.;
+
+    Self {
+      sample_rate,
+      ramp: Ramp::new(sample_rate, 5.),
+      curve: CrossfadeCurve::Smooth,
+      active_voice: 0,
+      start_gains: gains,
+      gains,
+    }
+  }
+
+  /// Returns the voice that's fading in or that's playing on its own.
+  pub fn get_active_voice(&self) -> usize {
+    self.active_voice
+  }
+
+  /// Returns a silent voice that can be started, or None when every voice is still audible.
+  pub fn get_free_voice(&self) -> Option<usize> {
+    (0..VOICES).find(|voice| *voice != self.active_voice && self.gains[*voice] == 0.)
+  }
+
+  /// Fades in the given voice over the given time in milliseconds.
+  pub fn start(&mut self, voice: usize, time: f32, curve: CrossfadeCurve) {
+    self.ramp = Ramp::new(self.sample_rate, 1000. / time.max(MIN_TIME));
+    self.ramp.start();
+    self.curve = curve;
+    self.active_voice = voice;
+    self.start_gains = self.gains;
+  }
+
+  pub fn is_finished(&self) -> bool {
+    self.ramp.is_finished()
+  }
+
+  /// Returns the gain of every voice. Voices with a gain of zero don't need to be processed.
+  pub fn process(&mut self) -> [f32; VOICES] {
+    if self.ramp.is_finished() {
+      return self.gains;
+    }
+
+    let (fade_in, fade_out) = self.curve.get_gains(self.ramp.process());
+    if self.ramp.is_finished() {
+      self.gains = [0.; VOICES];
+      self.gains[self.active_voice] = 1.;
+    } else {
+      for (voice, gain) in self.gains.iter_mut().enumerate() {
+        *gain = if voice == self.active_voice {
+          fade_in
+        } else {
+          self.start_gains[voice] * fade_out
+        };
+      }
+    }
+    self.gains
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Crossfade, CrossfadeCurve, VOICES};
+
+  #[test]
+  fn curves_start_and_end_at_the_extremes() {
+    for curve in [
+      CrossfadeCurve::Linear,
+      CrossfadeCurve::Smooth,
+      CrossfadeCurve::EqualPower,
+    ] {
+      let (fade_in, fade_out) = curve.get_gains(0.);
+      assert!(fade_in.abs() < 2e-3 && (fade_out - 1.).abs() < 2e-3);
+      let (fade_in, fade_out) = curve.get_gains(1.);
+      assert!((fade_in - 1.).abs() < 2e-3 && fade_out.abs() < 2e-3);
+    }
+  }
+
+  #[test]
+  fn crossfade_time_sets_the_duration() {
+    let mut crossfade = Crossfade::new(1000.);
+    crossfade.start(1, 20., CrossfadeCurve::Linear);
+    for _ in 0..20 {
+      assert!(!crossfade.is_finished());
+      crossfade.process();
+    }
+    assert_eq!(crossfade.process(), [0., 1., 0.]);
+    assert!(crossfade.is_finished());
+  }
+
+  #[test]
+  fn a_third_voice_can_start_during_a_crossfade() {
+    let mut crossfade = Crossfade::new(1000.);
+    crossfade.start(1, 20., CrossfadeCurve::Linear);
+    for _ in 0..11 {
+      crossfade.process();
+    }
+
+    let voice = crossfade.get_free_voice().unwrap();
+    assert_eq!(voice, 2);
+    crossfade.start(voice, 20., CrossfadeCurve::Linear);
+    let gains = crossfade.process();
+    assert!(gains[0] > 0. && gains[1] > 0. && gains[2] == 0.);
+    assert_eq!(crossfade.get_free_voice(), None);
+
+    for _ in 0..VOICES * 20 {
+      crossfade.process();
+    }
+    assert_eq!(crossfade.process(), [0., 0., 1.]);
+    assert_eq!(crossfade.get_free_voice(), Some(0));
+  }
+}
@@ -0,0 +1,42 @@
+use {crate::shared::sample::Sample, core::f32::consts::TAU};
+
+/// The cutoff frequency of the highpass filter in Hz.
+const FREQ: f32 = 20.;
+
+/// Removes the DC offset that asymmetric saturation adds to the wet signal.
+pub struct DcBlocker<T> {
+  r: T,
+  x1: T,
+  y1: T,
+}
+
+impl<T: Sample> DcBlocker<T> {
+  pub fn new(sample_rate: f32) -> Self {
+    Self {
+      r: T::from_f32(1. - TAU * FREQ / sample_rate),
+      x1: T::zero(),
+      y1: T::zero(),
+    }
+  }
+
+  pub fn process(&mut self, input: T) -> T {
+    let output = input - self.x1 + self.r * self.y1;
+    self.x1 = input;
+    self.y1 = output;
+    output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::DcBlocker;
+
+  #[test]
+  fn should_remove_dc() {
+    let mut dc_blocker = DcBlocker::<f32>::new(44100.);
+    for _ in 0..44100 {
+      dc_blocker.process(1.);
+    }
+    assert!(dc_blocker.process(1.).abs() < 1e-3);
+  }
+}
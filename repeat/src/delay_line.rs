@@ -1,7 +1,12 @@
-use std::f32::consts::PI;
-
-#[allow(dead_code)]
-#[derive(Clone, Copy)]
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+use {
+  crate::shared::sample::Sample,
+  alloc::{vec, vec::Vec},
+  core::f32::consts::PI,
+};
+
+#[derive(PartialEq, Clone, Copy)]
 pub enum Interpolation {
   Step,
   Linear,
@@ -10,26 +15,46 @@ pub enum Interpolation {
   Spline,
 }
 
+impl Interpolation {
+  /// Returns the interpolation for read positions that move between samples. Step reads fall back to linear interpolation.
+  pub fn to_fractional(self) -> Self {
+    match self {
+      Interpolation::Step => Interpolation::Linear,
+      _ => self,
+    }
+  }
+}
+
+/// Returns the offset in samples that a step interpolated read of the given time in milliseconds reads from.
+/// The read time is rounded to the nearest sample and is at least one sample.
+pub fn get_step_offset(time: f32, sample_rate: f32) -> usize {
+  (time * 0.001 * sample_rate - 0.5).max(1.).ceil() as usize
+}
+
 #[derive(Clone)]
-pub struct DelayLine {
-  buffer: Vec<f32>,
+pub struct DelayLine<T> {
+  buffer: Vec<T>,
   write_pointer: usize,
   sample_rate: f32,
   wrap: usize,
+  max_time: f32,
 }
 
-impl DelayLine {
+impl<T: Sample> DelayLine<T> {
   pub fn new(length: usize, sample_rate: f32) -> Self {
     let size = length.next_power_of_two();
     Self {
-      buffer: vec![0.0; size],
+      buffer: vec![T::zero(); size],
       write_pointer: 0,
       sample_rate,
       wrap: size - 1,
+      max_time: (size - 1) as f32 / sample_rate * 1000.,
     }
   }
 
-  pub fn read(&self, time: f32, interp: Interpolation) -> f32 {
+  /// Reads beyond the length of the buffer are clamped, so they don't wrap around to the most recent samples.
+  pub fn read(&self, time: f32, interp: Interpolation) -> T {
+    let time = time.min(self.max_time);
     match interp {
       Interpolation::Step => self.step_interp(time),
       Interpolation::Linear => self.linear_interp(time),
@@ -39,20 +64,21 @@ impl DelayLine {
     }
   }
 
-  pub fn write(&mut self, value: f32) {
+  pub fn write(&mut self, value: T) {
     self.buffer[self.write_pointer] = value;
     self.write_pointer = self.write_pointer + 1 & self.wrap;
   }
 
-  fn step_interp(&self, time: f32) -> f32 {
-    let read_pointer =
-      (self.write_pointer + self.buffer.len()) as f32 - (self.mstosamps(time) - 0.5).max(1.);
-    let index = read_pointer.trunc() as usize;
+  /// Reads the sample that was written the given number of samples ago.
+  pub fn read_offset(&self, offset: usize) -> T {
+    self.buffer[self.write_pointer.wrapping_sub(offset) & self.wrap]
+  }
 
-    self.buffer[index & self.wrap]
+  fn step_interp(&self, time: f32) -> T {
+    self.read_offset(get_step_offset(time, self.sample_rate))
   }
 
-  fn linear_interp(&self, time: f32) -> f32 {
+  fn linear_interp(&self, time: f32) -> T {
     let read_pointer =
       (self.write_pointer + self.buffer.len()) as f32 - self.mstosamps(time).max(1.);
     let rounded_read_pointer = read_pointer.trunc();
@@ -61,10 +87,10 @@ impl DelayLine {
 
     let x = self.buffer[index & self.wrap];
     let y = self.buffer[index + 1 & self.wrap];
-    x + (y - x) * mix
+    x.mix(y, mix)
   }
 
-  fn cosine_interp(&self, time: f32) -> f32 {
+  fn cosine_interp(&self, time: f32) -> T {
     let read_pointer =
       (self.write_pointer + self.buffer.len()) as f32 - self.mstosamps(time).max(1.);
     let rounded_read_pointer = read_pointer.trunc();
@@ -74,20 +100,23 @@ impl DelayLine {
     let cosine_mix = (1. - (mix * PI).cos()) / 2.;
     let x = self.buffer[index & self.wrap];
     let y = self.buffer[index + 1 & self.wrap];
-    x + (y - x) * cosine_mix
+    x.mix(y, cosine_mix)
   }
 
-  fn cubic_interp(&self, time: f32) -> f32 {
+  /// The four point interpolators read one sample on either side of the two samples they interpolate between, so reads
+  /// land on the same sample as the other interpolators. They read at least three samples back, so the newest point
+  /// doesn't wrap around to the oldest sample in the buffer.
+  fn cubic_interp(&self, time: f32) -> T {
     let read_pointer =
-      (self.write_pointer + self.buffer.len()) as f32 - self.mstosamps(time).max(2.);
+      (self.write_pointer + self.buffer.len()) as f32 - self.mstosamps(time).max(3.);
     let rounded_read_pointer = read_pointer.trunc();
     let mix = read_pointer - rounded_read_pointer;
     let index = rounded_read_pointer as usize;
 
-    let w = self.buffer[index & self.wrap];
-    let x = self.buffer[index + 1 & self.wrap];
-    let y = self.buffer[index + 2 & self.wrap];
-    let z = self.buffer[index + 3 & self.wrap];
+    let w = self.buffer[index.wrapping_sub(1) & self.wrap];
+    let x = self.buffer[index & self.wrap];
+    let y = self.buffer[index + 1 & self.wrap];
+    let z = self.buffer[index + 2 & self.wrap];
 
     let a1 = 1. + mix;
     let aa = mix * a1;
@@ -98,25 +127,27 @@ impl DelayLine {
     let fx = 0.5 * bb * a1;
     let fy = 0.5 * aa * b1;
     let fz = -0.1666667 * aa * b;
-    w * fw + x * fx + y * fy + z * fz
+    w * T::from_f32(fw) + x * T::from_f32(fx) + y * T::from_f32(fy) + z * T::from_f32(fz)
   }
 
-  fn spline_interp(&self, time: f32) -> f32 {
+  fn spline_interp(&self, time: f32) -> T {
     let read_pointer =
-      (self.write_pointer + self.buffer.len()) as f32 - self.mstosamps(time).max(2.);
+      (self.write_pointer + self.buffer.len()) as f32 - self.mstosamps(time).max(3.);
     let rounded_read_pointer = read_pointer.trunc();
     let mix = read_pointer - rounded_read_pointer;
     let index = rounded_read_pointer as usize;
 
-    let w = self.buffer[index & self.wrap];
-    let x = self.buffer[index + 1 & self.wrap];
-    let y = self.buffer[index + 2 & self.wrap];
-    let z = self.buffer[index + 3 & self.wrap];
+    let w = self.buffer[index.wrapping_sub(1) & self.wrap];
+    let x = self.buffer[index & self.wrap];
+    let y = self.buffer[index + 1 & self.wrap];
+    let z = self.buffer[index + 2 & self.wrap];
 
+    let half = T::from_f32(0.5);
+    let mix = T::from_f32(mix);
     let c0 = x;
-    let c1 = (0.5) * (y - w);
-    let c2 = w - (2.5) * x + y + y - (0.5) * z;
-    let c3 = (0.5) * (z - w) + (1.5) * (x - y);
+    let c1 = half * (y - w);
+    let c2 = w - T::from_f32(2.5) * x + y + y - half * z;
+    let c3 = half * (z - w) + T::from_f32(1.5) * (x - y);
     ((c3 * mix + c2) * mix + c1) * mix + c0
   }
 
@@ -124,3 +155,31 @@ impl DelayLine {
     time * 0.001 * self.sample_rate
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{DelayLine, Interpolation};
+
+  #[test]
+  fn every_interpolation_reads_the_same_sample() {
+    for interpolation in [
+      Interpolation::Step,
+      Interpolation::Linear,
+      Interpolation::Cosine,
+      Interpolation::Cubic,
+      Interpolation::Spline,
+    ] {
+      let mut delay_line = DelayLine::<f32>::new(64, 1000.);
+      let outputs: Vec<f32> = (0..20)
+        .map(|index| {
+          let output = delay_line.read(10., interpolation);
+          delay_line.write(if index == 0 { 1. } else { 0. });
+          output
+        })
+        .collect();
+      let impulse_position = outputs.iter().position(|output| *output == 1.);
+      assert_eq!(impulse_position, Some(10));
+      assert_eq!(outputs.iter().filter(|output| **output != 0.).count(), 1);
+    }
+  }
+}
@@ -1,33 +1,57 @@
-use crate::{
-  delay_line::{DelayLine, Interpolation},
-  shared::float_ext::FloatExt,
-  Params, MAX_REPEATS,
+use {
+  crate::{
+    delay_line::{get_step_offset, DelayLine, Interpolation},
+    drive::Drive,
+    lfo::Lfo,
+    pattern::CustomTap,
+    pitch_shifter::PitchShifter,
+    shared::sample::Sample,
+    spread::{get_pan, get_pan_gains},
+    tap_layout::{get_repeats, get_tap_layout},
+    tap_sum::{sum_taps, sum_taps_stereo, StereoGains, TapSummation},
+    tone_filter::ToneFilter,
+    Params, MAX_REPEATS,
+  },
+  alloc::vec::Vec,
 };
 
 #[derive(Clone)]
-struct DelayParams {
-  index: usize,
+struct DelayParams<T> {
   time: f32,
   gain: f32,
+  pan: f32,
+  pan_gains: (f32, f32),
+  tone_filter: ToneFilter<T>,
+  pitch_shifter: PitchShifter,
 }
 
-pub struct DelayLineRead {
-  previous_time: f32,
-  delay_params: Vec<DelayParams>,
+pub struct DelayLineRead<T> {
+  sample_rate: f32,
+  max_delay_time: f32,
+  delay_params: Vec<DelayParams<T>>,
+  /// The read offsets and gains of the taps, which are summed directly when every tap is a plain step interpolated read.
+  offsets: Vec<usize>,
+  gains: Vec<f32>,
+  stereo_gains: Vec<StereoGains>,
+  is_bypassed: bool,
+  drive: Drive,
   params: Params,
 }
 
-impl DelayLineRead {
-  pub fn new() -> Self {
+impl<T: Sample> DelayLineRead<T> {
+  pub fn new(sample_rate: f32, max_delay_time: f32) -> Self {
+    let params = Params::default();
+
     Self {
-      previous_time: 0.,
+      sample_rate,
+      max_delay_time,
       delay_params: Vec::with_capacity(MAX_REPEATS),
-      params: Params {
-        repeats: 4,
-        time: 200.,
-        feedback: 0.,
-        skew: 0.,
-      },
+      offsets: Vec::with_capacity(MAX_REPEATS),
+      gains: Vec::with_capacity(MAX_REPEATS),
+      stereo_gains: Vec::with_capacity(MAX_REPEATS),
+      is_bypassed: true,
+      drive: Drive::new(params.drive, params.drive_curve),
+      params,
     }
   }
 
@@ -35,132 +59,224 @@ impl DelayLineRead {
     self.params
   }
 
-  pub fn initialize(&mut self, time: f32, repeats: usize, feedback: f32, skew: f32) {
+  pub fn initialize(
+    &mut self,
+    params: &Params,
+    custom_taps: &[CustomTap],
+    custom_envelope: &[f32],
+  ) {
+    let Params {
+      time,
+      spread,
+      spread_mode,
+      tone,
+      tone_skew,
+      pitch,
+      reverse_mode,
+      drive,
+      drive_curve,
+      ..
+    } = *params;
+    let repeats = get_repeats(params, custom_taps);
+    let sample_rate = self.sample_rate;
+    let max_delay_time = self.max_delay_time;
+
+    // The taps are written into the preallocated buffer, so changing parameters doesn't allocate on the audio thread.
     self.delay_params.clear();
-    self.delay_params = (0..repeats)
-      .map(|index| {
-        let i = index as f32;
-        let gain = self.simulate_feedback(i, feedback, repeats);
-        let time = self.get_delay_time(i, time, skew);
+    // The first tap has no delay time. This is the dry signal, which is processed separately.
+    // Taps that don't fit in the delay line are left out, because clamping would stack them on the same time.
+    self.delay_params.extend(
+      get_tap_layout(params, custom_taps, custom_envelope)
+        .zip(1..repeats)
+        .filter(|(tap, _)| tap.time <= max_delay_time)
+        .map(|(tap, index)| {
+          let pan = get_pan(index, repeats, spread, spread_mode);
 
-        DelayParams { index, gain, time }
-      })
-      .collect();
-    self.params = Params {
-      time,
-      repeats,
-      feedback,
-      skew,
+          DelayParams {
+            gain: tap.gain,
+            time: tap.time,
+            pan,
+            pan_gains: get_pan_gains(pan),
+            tone_filter: ToneFilter::new(sample_rate, index, repeats, tone, tone_skew),
+            pitch_shifter: PitchShifter::new(
+              sample_rate,
+              index,
+              pitch,
+              time,
+              reverse_mode.is_reversed(index),
+            ),
+          }
+        }),
+    );
+    self.drive = Drive::new(drive, drive_curve);
+    self.params = *params;
+
+    self.offsets.clear();
+    self.gains.clear();
+    self.stereo_gains.clear();
+    for p in &self.delay_params {
+      self.offsets.push(get_step_offset(p.time, sample_rate));
+      self.gains.push(p.gain);
+      self
+        .stereo_gains
+        .push(Self::get_stereo_gains(p.gain, p.pan, p.pan_gains));
     }
+    self.is_bypassed = self.drive.is_bypassed()
+      && self
+        .delay_params
+        .iter()
+        .all(|p| p.tone_filter.is_bypassed() && p.pitch_shifter.is_bypassed());
   }
 
-  pub fn process(&mut self, input: f32, delay_line: &DelayLine) -> f32 {
+  pub fn process(
+    &mut self,
+    delay_line: &DelayLine<T>,
+    lfo: &Lfo,
+    interpolation: Interpolation,
+    summation: TapSummation,
+    glide_time: Option<f32>,
+  ) -> T {
+    let time_scale = self.get_time_scale(glide_time);
+    let interpolation = Self::get_interpolation(lfo, interpolation, time_scale);
+    if self.can_sum_taps(lfo, interpolation) {
+      return sum_taps(delay_line, &self.offsets, &self.gains, summation);
+    }
+
+    let drive = self.drive;
+    let max_delay_time = self.max_delay_time;
     self
       .delay_params
-      .iter()
-      .map(|p| {
-        let DelayParams { index, gain, time } = *p;
-
-        if index == 0 {
-          input * gain
-        } else {
-          delay_line.read(time, Interpolation::Step) * gain
-        }
+      .iter_mut()
+      .enumerate()
+      .map(|(i, p)| {
+        let time = Self::get_time(p.time, time_scale, max_delay_time) + lfo.get_modulation(i + 1);
+        let delayed = p.pitch_shifter.process(delay_line, time, interpolation);
+        drive.process(p.tone_filter.process(delayed, 0) * T::from_f32(p.gain))
       })
       .sum()
   }
 
-  fn reverse_indices(&self, index: f32, input: f32, repeats: usize) -> f32 {
-    if input.signum() == 1. {
-      index
-    } else {
-      repeats as f32 - index - 1.0
+  pub fn process_stereo(
+    &mut self,
+    delay_lines: &[DelayLine<T>; 2],
+    lfo: &Lfo,
+    interpolation: Interpolation,
+    summation: TapSummation,
+    glide_time: Option<f32>,
+  ) -> (T, T) {
+    let time_scale = self.get_time_scale(glide_time);
+    let interpolation = Self::get_interpolation(lfo, interpolation, time_scale);
+    if self.can_sum_taps(lfo, interpolation) {
+      return sum_taps_stereo(delay_lines, &self.offsets, &self.stereo_gains, summation);
     }
+
+    let drive = self.drive;
+    let max_delay_time = self.max_delay_time;
+    self
+      .delay_params
+      .iter_mut()
+      .enumerate()
+      .map(|(i, p)| {
+        let time = Self::get_time(p.time, time_scale, max_delay_time) + lfo.get_modulation(i + 1);
+        let (left, right) = p
+          .pitch_shifter
+          .process_stereo(delay_lines, time, interpolation);
+        let gain = T::from_f32(p.gain);
+        let left = drive.process(p.tone_filter.process(left, 0) * gain);
+        let right = drive.process(p.tone_filter.process(right, 1) * gain);
+        Self::apply_pan(left, right, p.pan, p.pan_gains)
+      })
+      .fold((T::zero(), T::zero()), |sum, (left, right)| {
+        (sum.0 + left, sum.1 + right)
+      })
+  }
+
+  /// The taps can be summed directly when they're not modulated and none of the tap processing is active.
+  fn can_sum_taps(&self, lfo: &Lfo, interpolation: Interpolation) -> bool {
+    self.is_bypassed && !lfo.is_active() && interpolation == Interpolation::Step
   }
 
-  fn simulate_feedback(&self, index: f32, feedback: f32, repeats: usize) -> f32 {
-    let feedback_index = self.reverse_indices(index, feedback, repeats);
-    let absolute_feedback = feedback.abs();
-    if absolute_feedback == 1. {
-      1.
+  /// Every tap time scales with the time, so gliding taps are read at the glide time relative to the time of the taps.
+  fn get_time_scale(&self, glide_time: Option<f32>) -> f32 {
+    match glide_time {
+      Some(glide_time) if self.params.time > 0. => glide_time / self.params.time,
+      _ => 1.,
+    }
+  }
+
+  fn get_time(time: f32, time_scale: f32, max_delay_time: f32) -> f32 {
+    if time_scale == 1. {
+      time
     } else {
-      absolute_feedback.fast_pow(feedback_index)
+      (time * time_scale).min(max_delay_time)
     }
   }
 
-  fn get_delay_time(&mut self, index: f32, time: f32, skew: f32) -> f32 {
-    if index == 0. {
-      self.previous_time = 0.0;
-      0.0
-    } else if skew == 0. {
-      time * index
+  /// Modulated and gliding read times need a fractional read to move smoothly through the delay line.
+  fn get_interpolation(lfo: &Lfo, interpolation: Interpolation, time_scale: f32) -> Interpolation {
+    if lfo.is_active() || time_scale != 1. {
+      interpolation.to_fractional()
     } else {
-      let exponential_skew = skew * skew * if skew < 0. { -0.5 } else { 1. } + 1.;
-      let delay_time = if index == 1. {
-        exponential_skew.fast_pow(index - 1.) * time
-      } else {
-        exponential_skew.fast_pow(index - 1.) * time + self.previous_time
+      interpolation
+    }
+  }
+
+  /// Returns the gains of `apply_pan` for each input channel, so the panning can be applied while summing the taps.
+  fn get_stereo_gains(gain: f32, pan: f32, pan_gains: (f32, f32)) -> StereoGains {
+    if pan == 0. {
+      return StereoGains {
+        left_to_left: gain,
+        right_to_right: gain,
+        ..Default::default()
       };
-      self.previous_time = delay_time;
-      delay_time
+    }
+
+    let cross_gain = pan.abs() * 0.5;
+    StereoGains {
+      left_to_left: gain * (1. - cross_gain) * pan_gains.0,
+      right_to_left: gain * cross_gain * pan_gains.0,
+      left_to_right: gain * cross_gain * pan_gains.1,
+      right_to_right: gain * (1. - cross_gain) * pan_gains.1,
     }
   }
+
+  /// Narrows the stereo image of the repeat towards mono as it's panned further from the center.
+  fn apply_pan(left: T, right: T, pan: f32, pan_gains: (f32, f32)) -> (T, T) {
+    if pan == 0. {
+      return (left, right);
+    }
+
+    let mono = (left + right) * T::from_f32(0.5);
+    let width = pan.abs();
+    (
+      left.mix(mono, width) * T::from_f32(pan_gains.0),
+      right.mix(mono, width) * T::from_f32(pan_gains.1),
+    )
+  }
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::delay_line_read::DelayLineRead;
+  use super::DelayLineRead;
+  use crate::{Params, MAX_REPEATS};
 
   #[test]
-  fn feedback() {
-    let repeater = DelayLineRead::new();
-    assert_eq!(repeater.simulate_feedback(0.0, 1.0, 4), 1.0);
-    assert_eq!(repeater.simulate_feedback(1.0, 1.0, 4), 1.0);
-    assert_eq!(repeater.simulate_feedback(2.0, 1.0, 4), 1.0);
-    assert_eq!(repeater.simulate_feedback(3.0, 1.0, 4), 1.0);
-
-    assert_eq!(repeater.simulate_feedback(0.0, 2.0, 4), 1.0);
-    assert_eq!(repeater.simulate_feedback(1.0, 2.0, 4), 2.0);
-    assert_eq!(repeater.simulate_feedback(2.0, 2.0, 4), 4.0);
-    assert_eq!(repeater.simulate_feedback(3.0, 2.0, 4), 8.0);
-
-    assert_eq!(repeater.simulate_feedback(0.0, 0.5, 4), 1.0);
-    assert_eq!(repeater.simulate_feedback(1.0, 0.5, 4), 0.5);
-    assert_eq!(repeater.simulate_feedback(2.0, 0.5, 4), 0.25);
-    assert_eq!(repeater.simulate_feedback(3.0, 0.5, 4), 0.125);
-
-    assert_eq!(repeater.simulate_feedback(0.0, -1.0, 4), 1.0);
-    assert_eq!(repeater.simulate_feedback(1.0, -1.0, 4), 1.0);
-    assert_eq!(repeater.simulate_feedback(2.0, -1.0, 4), 1.0);
-    assert_eq!(repeater.simulate_feedback(3.0, -1.0, 4), 1.0);
-
-    assert_eq!(repeater.simulate_feedback(0.0, -2.0, 4), 8.0);
-    assert_eq!(repeater.simulate_feedback(1.0, -2.0, 4), 4.0);
-    assert_eq!(repeater.simulate_feedback(2.0, -2.0, 4), 2.0);
-    assert_eq!(repeater.simulate_feedback(3.0, -2.0, 4), 1.0);
-
-    assert_eq!(repeater.simulate_feedback(0.0, -0.5, 4), 0.125);
-    assert_eq!(repeater.simulate_feedback(1.0, -0.5, 4), 0.25);
-    assert_eq!(repeater.simulate_feedback(2.0, -0.5, 4), 0.5);
-    assert_eq!(repeater.simulate_feedback(3.0, -0.5, 4), 1.0);
-  }
+  fn initialize_reuses_the_tap_buffer() {
+    let mut delay_line_read = DelayLineRead::<f32>::new(44100., 10000.);
+    let buffer = delay_line_read.delay_params.as_ptr();
 
-  #[test]
-  fn delay_time() {
-    let mut repeater = DelayLineRead::new();
-    assert_eq!(repeater.get_delay_time(0.0, 100.0, 0.0), 0.0);
-    assert_eq!(repeater.get_delay_time(1.0, 100.0, 0.0), 100.0);
-    assert_eq!(repeater.get_delay_time(2.0, 100.0, 0.0), 200.0);
-    assert_eq!(repeater.get_delay_time(3.0, 100.0, 0.0), 300.0);
-
-    assert_eq!(repeater.get_delay_time(0.0, 100.0, 1.0), 0.0);
-    assert_eq!(repeater.get_delay_time(1.0, 100.0, 1.0), 100.0);
-    assert_eq!(repeater.get_delay_time(2.0, 100.0, 1.0), 300.0);
-    assert_eq!(repeater.get_delay_time(3.0, 100.0, 1.0), 700.0);
-
-    assert_eq!(repeater.get_delay_time(0.0, 100.0, -1.0), 0.0);
-    assert_eq!(repeater.get_delay_time(1.0, 100.0, -1.0), 100.0);
-    assert_eq!(repeater.get_delay_time(2.0, 100.0, -1.0), 150.0);
-    assert_eq!(repeater.get_delay_time(3.0, 100.0, -1.0), 175.0);
+    for repeats in [MAX_REPEATS, 4, MAX_REPEATS * 2] {
+      delay_line_read.initialize(
+        &Params {
+          time: 100.,
+          repeats,
+          ..Default::default()
+        },
+        &[],
+        &[],
+      );
+      assert_eq!(delay_line_read.delay_params.as_ptr(), buffer);
+    }
+    assert_eq!(delay_line_read.delay_params.len(), MAX_REPEATS - 1);
   }
 }
@@ -0,0 +1,98 @@
+use {
+  crate::shared::{float_ext::FloatExt, sample::Sample},
+  core::f32::consts::FRAC_2_PI,
+};
+
+/// The maximum gain in decibels that's applied before the curve.
+const MAX_DRIVE: f32 = 24.;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum DriveCurve {
+  Tanh,
+  Atan,
+  SoftClip,
+  /// Saturates the positive half harder than the negative half, which adds even harmonics and a DC offset.
+  Tube,
+}
+
+/// Saturates every tap, so feedback settings above unity gain stay bounded.
+#[derive(Clone, Copy)]
+pub struct Drive {
+  gain: f32,
+  curve: DriveCurve,
+  is_bypassed: bool,
+}
+
+impl Drive {
+  /// The drive amount is a value between 0 and 1. At zero the drive stage is bypassed.
+  pub fn new(drive: f32, curve: DriveCurve) -> Self {
+    Self {
+      gain: (drive * MAX_DRIVE).dbtoa(),
+      curve,
+      is_bypassed: drive == 0.,
+    }
+  }
+
+  pub fn is_bypassed(&self) -> bool {
+    self.is_bypassed
+  }
+
+  pub fn process<T: Sample>(&self, input: T) -> T {
+    if self.is_bypassed {
+      return input;
+    }
+
+    let x = input * T::from_f32(self.gain);
+    let one = T::one();
+    let three = T::from_f32(3.);
+    match self.curve {
+      DriveCurve::Tanh => x.clamp(-three, three).fast_tanh(),
+      DriveCurve::Atan => x.atan() * T::from_f32(FRAC_2_PI),
+      DriveCurve::SoftClip => {
+        let x = x.clamp(-one, one);
+        T::from_f32(1.5) * (x - x * x * x / three)
+      }
+      DriveCurve::Tube => {
+        if x > T::zero() {
+          x.clamp(-three, three).fast_tanh()
+        } else {
+          x / (one - x * T::from_f32(0.5))
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Drive, DriveCurve};
+
+  #[test]
+  fn should_bypass_without_drive() {
+    let drive = Drive::new(0., DriveCurve::SoftClip);
+    assert_eq!(drive.process(4.), 4.);
+  }
+
+  #[test]
+  fn curves_stay_bounded() {
+    for curve in [
+      DriveCurve::Tanh,
+      DriveCurve::Atan,
+      DriveCurve::SoftClip,
+      DriveCurve::Tube,
+    ] {
+      let drive = Drive::new(1., curve);
+      for input in [-100_f32, -1., -0.1, 0., 0.1, 1., 100.] {
+        let output = drive.process(input);
+        assert!(output.abs() <= 2., "{output} is out of bounds");
+        assert_eq!(output.signum(), f32::signum(input));
+      }
+    }
+  }
+
+  #[test]
+  fn tube_is_asymmetric() {
+    let drive = Drive::new(0.5, DriveCurve::Tube);
+    assert!(drive.process(0.5) < -drive.process(-0.5));
+  }
+}
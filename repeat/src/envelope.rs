@@ -0,0 +1,102 @@
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+use {crate::shared::random::random, core::f32::consts::PI};
+
+const RANDOM_SEED: u32 = 0xe4e1;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum EnvelopeShape {
+  /// Multiplies the gain by the feedback for every repeat.
+  Exponential,
+  Linear,
+  /// Swells in and decays again, peaking halfway the repeats.
+  Bell,
+  /// Keeps every repeat at the same level.
+  Gate,
+  Random,
+  /// Follows the gains set with `Repeat::set_custom_envelope`.
+  Custom,
+}
+
+/// Returns the gain of the tap at the given position, which counts from zero up to the number of taps.
+/// The exponential envelope is simulated by `DelayLineRead`, because it depends on the feedback.
+pub fn get_envelope_gain(
+  position: usize,
+  taps: usize,
+  shape: EnvelopeShape,
+  custom: &[f32],
+) -> f32 {
+  let taps = taps.max(1) as f32;
+  let position = position as f32;
+
+  match shape {
+    EnvelopeShape::Linear => 1. - position / taps,
+    EnvelopeShape::Bell => ((position + 0.5) / taps * PI).sin(),
+    EnvelopeShape::Exponential | EnvelopeShape::Gate => 1.,
+    EnvelopeShape::Random => random(RANDOM_SEED + position as u32),
+    EnvelopeShape::Custom => get_custom_gain(position / (taps - 1.).max(1.), custom),
+  }
+}
+
+/// Interpolates linearly between the points of the custom envelope, which are spread evenly over the taps.
+fn get_custom_gain(x: f32, custom: &[f32]) -> f32 {
+  match custom.len() {
+    0 => 1.,
+    1 => custom[0],
+    length => {
+      let position = x * (length - 1) as f32;
+      let index = (position.trunc() as usize).min(length - 2);
+      let mix = position - index as f32;
+      custom[index] + (custom[index + 1] - custom[index]) * mix
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{get_envelope_gain, EnvelopeShape};
+
+  fn get_gains(taps: usize, shape: EnvelopeShape, custom: &[f32]) -> Vec<f32> {
+    (0..taps)
+      .map(|position| get_envelope_gain(position, taps, shape, custom))
+      .collect()
+  }
+
+  #[test]
+  fn linear() {
+    assert_eq!(
+      get_gains(4, EnvelopeShape::Linear, &[]),
+      vec![1., 0.75, 0.5, 0.25]
+    );
+  }
+
+  #[test]
+  fn bell_swells_and_decays() {
+    let gains = get_gains(5, EnvelopeShape::Bell, &[]);
+    assert_eq!(gains[2], 1.);
+    assert!(gains[0] < gains[1] && gains[1] < gains[2]);
+    assert!((gains[0] - gains[4]).abs() < 1e-6);
+  }
+
+  #[test]
+  fn gate() {
+    assert_eq!(get_gains(3, EnvelopeShape::Gate, &[]), vec![1., 1., 1.]);
+  }
+
+  #[test]
+  fn random_is_seeded() {
+    let gains = get_gains(8, EnvelopeShape::Random, &[]);
+    assert_eq!(gains, get_gains(8, EnvelopeShape::Random, &[]));
+    assert!(gains.iter().all(|gain| (0. ..1.).contains(gain)));
+  }
+
+  #[test]
+  fn custom_interpolates_between_points() {
+    assert_eq!(
+      get_gains(5, EnvelopeShape::Custom, &[0., 1., 0.]),
+      vec![0., 0.5, 1., 0.5, 0.]
+    );
+    assert_eq!(get_gains(2, EnvelopeShape::Custom, &[]), vec![1., 1.]);
+    assert_eq!(get_gains(2, EnvelopeShape::Custom, &[0.5]), vec![0.5, 0.5]);
+  }
+}
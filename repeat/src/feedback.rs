@@ -0,0 +1,51 @@
+use crate::{shared::sample::Sample, Params};
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum FeedbackMode {
+  /// Every tap gets a fixed gain, so the tail ends after the last repeat.
+  Simulated,
+  /// The sum of the taps is fed back into the delay line, so the tail keeps decaying.
+  Recirculating,
+}
+
+/// Returns the signal that's fed back into the delay line. The sum of the taps is normalized by the number of taps
+/// and saturated, so the loop stays bounded when the feedback exceeds unity.
+/// The repeats include the dry signal and come from `get_repeats`, so the custom pattern is normalized by its own taps.
+pub fn get_feedback<T: Sample>(wet: T, params: &Params, repeats: usize) -> T {
+  match params.feedback_mode {
+    FeedbackMode::Simulated => T::zero(),
+    FeedbackMode::Recirculating => {
+      let taps = repeats.saturating_sub(1).max(1) as f32;
+      (wet / T::from_f32(taps) * T::from_f32(params.feedback)).tanh()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{get_feedback, FeedbackMode};
+  use crate::Params;
+
+  #[test]
+  fn simulated_mode_has_no_feedback() {
+    assert_eq!(get_feedback(1_f32, &Params::default(), 4), 0.);
+  }
+
+  #[test]
+  fn recirculating_feedback_is_normalized_and_bounded() {
+    let params = Params {
+      feedback: 0.5,
+      feedback_mode: FeedbackMode::Recirculating,
+      ..Default::default()
+    };
+    let feedback = get_feedback(0.04_f32, &params, 5);
+    assert!((feedback - 0.005).abs() < 1e-3);
+
+    let params = Params {
+      feedback: 1.25,
+      ..params
+    };
+    assert!(get_feedback(100_f32, &params, 5) <= 1.);
+    assert!(get_feedback(-100_f32, &params, 5) >= -1.);
+  }
+}
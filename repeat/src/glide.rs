@@ -0,0 +1,56 @@
+#[derive(PartialEq, Clone, Copy)]
+pub enum TimeMode {
+  /// Time changes crossfade to taps at the new time.
+  Crossfade,
+  /// Time changes glide at a limited rate like the motor of a tape delay, which bends the pitch of the repeats.
+  Tape,
+}
+
+/// A slew limiter for the time in milliseconds. The taps scale with the time, so later taps glide further and bend more.
+pub struct Glide {
+  value: f32,
+  sample_period: f32,
+}
+
+impl Glide {
+  pub fn new(sample_rate: f32) -> Self {
+    Self {
+      value: 0.,
+      sample_period: sample_rate.recip(),
+    }
+  }
+
+  pub fn initialize(&mut self, value: f32) {
+    self.value = value;
+  }
+
+  pub fn is_finished(&self, target: f32) -> bool {
+    self.value == target
+  }
+
+  /// Moves towards the target by at most the given rate in milliseconds per second.
+  pub fn process(&mut self, target: f32, rate: f32) -> f32 {
+    let max_step = rate * self.sample_period;
+    self.value += (target - self.value).clamp(-max_step, max_step);
+    self.value
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Glide;
+
+  #[test]
+  fn glides_linearly_to_the_target() {
+    let mut glide = Glide::new(1000.);
+    glide.initialize(10.);
+    assert_eq!(glide.process(20., 2000.), 12.);
+    assert_eq!(glide.process(20., 2000.), 14.);
+    for _ in 0..3 {
+      glide.process(20., 2000.);
+    }
+    assert!(glide.is_finished(20.));
+    assert_eq!(glide.process(20., 2000.), 20.);
+    assert_eq!(glide.process(5., 4000.), 16.);
+  }
+}
@@ -0,0 +1,167 @@
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+use {
+  crate::{
+    shared::{float_ext::FloatExt, param_filter::ParamFilter, random::random},
+    Params,
+  },
+  core::f32::consts::TAU,
+};
+
+/// Below this depth in milliseconds the modulation is considered to be inactive.
+const MIN_DEPTH: f32 = 1e-4;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum LfoShape {
+  Sine,
+  Triangle,
+  Random,
+}
+
+/// Modulates the read times of the taps. Every tap reads the LFO at its own phase offset.
+pub struct Lfo {
+  /// The phase within the current cycle, between 0 and 1. It's kept small, so the increment doesn't lose precision.
+  phase: f32,
+  /// The number of cycles that have passed, which seeds the random shape.
+  cycle: u32,
+  sample_period: f32,
+  smooth_depth: ParamFilter,
+  depth: f32,
+  phase_offset: f32,
+  shape: LfoShape,
+}
+
+impl Lfo {
+  pub fn new(sample_rate: f32) -> Self {
+    Self {
+      phase: 0.,
+      cycle: 0,
+      sample_period: sample_rate.recip(),
+      smooth_depth: ParamFilter::new(sample_rate, 12.),
+      depth: 0.,
+      phase_offset: 0.,
+      shape: LfoShape::Sine,
+    }
+  }
+
+  pub fn initialize(&mut self, params: &Params) {
+    self.smooth_depth.initialize(params.lfo_depth);
+    self.depth = params.lfo_depth;
+    self.phase_offset = params.lfo_phase_offset;
+    self.shape = params.lfo_shape;
+  }
+
+  /// Call this once for every sample before reading the modulation of the taps.
+  pub fn process(&mut self, params: &Params) {
+    let phase = self.phase + params.lfo_rate * self.sample_period;
+    self.cycle = self.cycle.wrapping_add(phase.trunc() as u32);
+    self.phase = phase.fract();
+    self.depth = self.smooth_depth.process(params.lfo_depth);
+    self.phase_offset = params.lfo_phase_offset;
+    self.shape = params.lfo_shape;
+  }
+
+  pub fn is_active(&self) -> bool {
+    self.depth > MIN_DEPTH
+  }
+
+  /// Returns the extra delay time in milliseconds for the tap at the given index.
+  pub fn get_modulation(&self, index: usize) -> f32 {
+    if !self.is_active() {
+      return 0.;
+    }
+
+    let phase = self.phase + self.phase_offset * index as f32;
+    (Self::get_value(phase, self.cycle, self.shape) + 1.) * 0.5 * self.depth
+  }
+
+  /// Returns the value of the shape between -1 and 1 at the given phase, counting from the start of the given cycle.
+  fn get_value(phase: f32, cycle: u32, shape: LfoShape) -> f32 {
+    match shape {
+      LfoShape::Sine => (phase.fract() * TAU).fast_sin(),
+      LfoShape::Triangle => 1. - (phase.fract() * 4. - 2.).abs(),
+      LfoShape::Random => {
+        let cycle = cycle.wrapping_add(phase.trunc() as u32);
+        let current = random(cycle);
+        let next = random(cycle.wrapping_add(1));
+        let mix = (1. - (phase.fract() * TAU * 0.5).fast_cos()) * 0.5;
+        current.mix(next, mix) * 2. - 1.
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Lfo, LfoShape};
+  use crate::Params;
+
+  fn assert_approximately_eq(left: f32, right: f32) {
+    assert!((left - right).abs() < 1e-3, "left: {left}, right: {right}");
+  }
+
+  #[test]
+  fn sine() {
+    assert_approximately_eq(Lfo::get_value(0., 0, LfoShape::Sine), 0.);
+    assert_approximately_eq(Lfo::get_value(0.25, 0, LfoShape::Sine), 1.);
+    assert_approximately_eq(Lfo::get_value(1.75, 0, LfoShape::Sine), -1.);
+  }
+
+  #[test]
+  fn triangle() {
+    assert_approximately_eq(Lfo::get_value(0., 0, LfoShape::Triangle), -1.);
+    assert_approximately_eq(Lfo::get_value(0.25, 0, LfoShape::Triangle), 0.);
+    assert_approximately_eq(Lfo::get_value(0.5, 0, LfoShape::Triangle), 1.);
+    assert_approximately_eq(Lfo::get_value(1.75, 0, LfoShape::Triangle), 0.);
+  }
+
+  #[test]
+  fn random_is_smooth_across_cycles() {
+    let end_of_cycle = Lfo::get_value(1. - 1e-3, 5, LfoShape::Random);
+    let start_of_cycle = Lfo::get_value(0., 6, LfoShape::Random);
+    assert_approximately_eq(end_of_cycle, start_of_cycle);
+    let end_of_counter = Lfo::get_value(1. - 1e-3, u32::MAX, LfoShape::Random);
+    let wrapped_counter = Lfo::get_value(0., 0, LfoShape::Random);
+    assert_approximately_eq(end_of_counter, wrapped_counter);
+    assert!((-1. ..=1.).contains(&Lfo::get_value(3.3, 0, LfoShape::Random)));
+  }
+
+  #[test]
+  fn slow_rates_keep_moving_after_many_cycles() {
+    let params = Params {
+      lfo_rate: 0.05,
+      ..Default::default()
+    };
+    let mut lfo = Lfo::new(48000.);
+    lfo.initialize(&params);
+    lfo.cycle = 1_000_000;
+    for _ in 0..48000 {
+      lfo.process(&params);
+    }
+    assert_approximately_eq(lfo.phase, 0.05);
+  }
+
+  #[test]
+  fn modulation_is_unipolar_and_offset_per_tap() {
+    let params = Params {
+      lfo_depth: 2.,
+      lfo_phase_offset: 0.25,
+      ..Default::default()
+    };
+    let mut lfo = Lfo::new(1000.);
+    lfo.initialize(&params);
+
+    assert_approximately_eq(lfo.get_modulation(0), 1.);
+    assert_approximately_eq(lfo.get_modulation(1), 2.);
+    assert_approximately_eq(lfo.get_modulation(3), 0.);
+  }
+
+  #[test]
+  fn should_be_inactive_without_depth() {
+    let mut lfo = Lfo::new(1000.);
+    lfo.initialize(&Params::default());
+    lfo.process(&Params::default());
+    assert!(!lfo.is_active());
+    assert_eq!(lfo.get_modulation(1), 0.);
+  }
+}
@@ -1,147 +1,1016 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+extern crate alloc;
+
+mod crossfade;
+mod dc_blocker;
 mod delay_line;
 mod delay_line_read;
+mod drive;
+mod envelope;
+mod feedback;
+mod glide;
+mod lfo;
 mod shared {
   pub mod float_ext;
+  pub mod param_filter;
+  pub mod random;
+  pub mod sample;
 }
 mod limiter;
+mod params;
+mod pattern;
+mod pitch_shifter;
 mod ramp;
+mod spread;
+mod tap_layout;
+mod tap_sum;
+mod tap_tempo;
+mod tone_filter;
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
 use {
-  delay_line::DelayLine, delay_line_read::DelayLineRead, limiter::Limiter, ramp::Ramp,
-  shared::float_ext::FloatExt, std::f32, std::f32::consts::FRAC_PI_2,
+  alloc::vec::Vec,
+  crossfade::{Crossfade, VOICES},
+  dc_blocker::DcBlocker,
+  delay_line::DelayLine,
+  delay_line_read::DelayLineRead,
+  feedback::get_feedback,
+  glide::Glide,
+  lfo::Lfo,
+  limiter::Limiter,
+  ramp::Ramp,
+  shared::param_filter::ParamFilter,
+};
+pub use {
+  crossfade::CrossfadeCurve,
+  delay_line::Interpolation,
+  drive::DriveCurve,
+  envelope::EnvelopeShape,
+  feedback::FeedbackMode,
+  glide::TimeMode,
+  lfo::LfoShape,
+  params::Params,
+  pattern::{CustomTap, Pattern},
+  pitch_shifter::ReverseMode,
+  shared::sample::Sample,
+  spread::SpreadMode,
+  tap_layout::Tap,
+  tap_sum::TapSummation,
+  tap_tempo::TapTempo,
 };
 
-pub const MAX_REPEATS: usize = 32;
+pub const MAX_REPEATS: usize = 64;
+/// The number of points of the custom envelope in the plugins, so an envelope sounds the same in every format.
+/// `Repeat::set_custom_envelope` accepts up to `MAX_REPEATS` points.
+pub const CUSTOM_ENVELOPE_POINTS: usize = 8;
+/// The maximum delay time in milliseconds of `Repeat::new`.
+pub const DEFAULT_MAX_DELAY_TIME: f32 = 10000.;
 
-#[derive(PartialEq, Clone, Copy)]
-pub struct Params {
-  repeats: usize,
-  time: f32,
-  feedback: f32,
-  skew: f32,
+/// The delay engine, which processes `f32` samples by default. Use `Repeat<f64>` to process double precision samples natively.
+/// The parameters are `f32` regardless of the sample type.
+pub struct Repeat<T: Sample = f32> {
+  delay_lines: [DelayLine<T>; 2],
+  dry_delay_lines: [DelayLine<T>; 2],
+  repeats: [DelayLineRead<T>; VOICES],
+  crossfade: Crossfade,
+  glide: Glide,
+  limiter: Limiter<T>,
+  limiter_latency: f32,
+  dry_delay_ramps: [Ramp; 2],
+  is_dry_delayed: bool,
+  dry_gain: ParamFilter,
+  wet_gain: ParamFilter,
+  freeze_ramps: [Ramp; 2],
+  is_frozen: bool,
+  lfo: Lfo,
+  dc_blockers: [DcBlocker<T>; 2],
+  custom_taps: Vec<CustomTap>,
+  custom_envelope: Vec<f32>,
+  custom_shapes_have_changed: bool,
+  tap_summation: TapSummation,
+  max_delay_time: f32,
 }
 
-pub struct Repeat {
-  delay_line: DelayLine,
-  active_index: usize,
-  repeats: [DelayLineRead; 2],
-  ramp: Ramp,
-  limiter: Limiter,
+impl Repeat {
+  /// Returns the time and gain of every repeat for the given parameters, without processing any audio.
+  /// Pass the same custom taps and envelope that were set on the instance to get the layout of the custom pattern and envelope.
+  /// Taps beyond the maximum delay time of an instance are included, but that instance doesn't render them.
+  pub fn tap_layout<'a>(
+    params: &Params,
+    custom_taps: &'a [CustomTap],
+    custom_envelope: &'a [f32],
+  ) -> impl Iterator<Item = Tap> + 'a {
+    tap_layout::get_tap_layout(params, custom_taps, custom_envelope)
+  }
 }
 
-impl Repeat {
+impl<T: Sample> Repeat<T> {
   pub fn new(sample_rate: f32) -> Self {
+    Self::with_max_delay_time(sample_rate, DEFAULT_MAX_DELAY_TIME)
+  }
+
+  /// Sizes the delay lines for the given maximum delay time in milliseconds. Taps beyond the maximum delay time are
+  /// left out, so hosts with little memory can use shorter delay lines.
+  pub fn with_max_delay_time(sample_rate: f32, max_delay_time: f32) -> Self {
+    let delay_line = DelayLine::new(
+      (max_delay_time * 0.001 * sample_rate).ceil() as usize + 2,
+      sample_rate,
+    );
+    let limiter = Limiter::<T>::new(sample_rate, 2., 10., 40., 0.966051);
+    let dry_delay_line = DelayLine::new(limiter.get_latency() + 1, sample_rate);
+
     Self {
-      delay_line: DelayLine::new(sample_rate as usize * 10, sample_rate),
-      active_index: 0,
-      repeats: [DelayLineRead::new(), DelayLineRead::new()],
-      ramp: Ramp::new(sample_rate, 5.),
-      limiter: Limiter::new(sample_rate, 2., 10., 40., 0.966051),
+      delay_lines: [delay_line.clone(), delay_line],
+      dry_delay_lines: [dry_delay_line.clone(), dry_delay_line],
+      repeats: core::array::from_fn(|_| DelayLineRead::new(sample_rate, max_delay_time)),
+      crossfade: Crossfade::new(sample_rate),
+      glide: Glide::new(sample_rate),
+      limiter_latency: limiter.get_latency() as f32 / sample_rate * 1000.,
+      limiter,
+      dry_delay_ramps: [Ramp::new(sample_rate, -20.), Ramp::new(sample_rate, 20.)],
+      is_dry_delayed: false,
+      dry_gain: ParamFilter::new(sample_rate, 12.),
+      wet_gain: ParamFilter::new(sample_rate, 12.),
+      freeze_ramps: [Ramp::new(sample_rate, -5.), Ramp::new(sample_rate, 5.)],
+      is_frozen: false,
+      lfo: Lfo::new(sample_rate),
+      dc_blockers: [DcBlocker::new(sample_rate), DcBlocker::new(sample_rate)],
+      custom_taps: Vec::with_capacity(MAX_REPEATS - 1),
+      custom_envelope: Vec::with_capacity(MAX_REPEATS),
+      custom_shapes_have_changed: false,
+      tap_summation: TapSummation::Chunked,
+      max_delay_time,
+    }
+  }
+
+  /// Returns the maximum delay time in milliseconds. Taps of the tap layout beyond this time are left out.
+  pub fn get_max_delay_time(&self) -> f32 {
+    self.max_delay_time
+  }
+
+  /// Sets the taps that are used by the custom pattern. The first tap is the dry signal, so up to `MAX_REPEATS - 1` taps are used.
+  /// Negative times and gains are clamped to zero and taps that aren't finite are left out.
+  /// Call this outside of the audio thread, or make sure the taps fit in the preallocated capacity.
+  pub fn set_custom_taps(&mut self, custom_taps: &[CustomTap]) {
+    self.custom_taps.clear();
+    self.custom_taps.extend(
+      custom_taps
+        .iter()
+        .filter_map(CustomTap::validate)
+        .take(MAX_REPEATS - 1),
+    );
+    self.custom_shapes_have_changed = true;
+  }
+
+  /// Sets the gains of the custom envelope, which are spread evenly over the repeats. Up to `MAX_REPEATS` points are used.
+  pub fn set_custom_envelope(&mut self, gains: &[f32]) {
+    self.custom_envelope.clear();
+    self.custom_envelope.extend(gains.iter().take(MAX_REPEATS));
+    self.custom_shapes_have_changed = true;
+  }
+
+  /// Sets how the taps are summed when they're plain delay line reads. Chunked is the default, Scalar is kept for
+  /// comparison.
+  pub fn set_tap_summation(&mut self, tap_summation: TapSummation) {
+    self.tap_summation = tap_summation;
+  }
+
+  pub fn initialize_params(&mut self, params: &Params) {
+    self.repeats[self.crossfade.get_active_voice()].initialize(
+      params,
+      &self.custom_taps,
+      &self.custom_envelope,
+    );
+    self.custom_shapes_have_changed = false;
+    self.glide.initialize(params.time);
+    let (dry_gain, wet_gain) = Self::get_mix_gains(params);
+    self.dry_gain.initialize(dry_gain);
+    self.wet_gain.initialize(wet_gain);
+    self.lfo.initialize(params);
+    self.is_dry_delayed = params.limiter;
+    self.dry_delay_ramps[params.limiter as usize].start_from(if params.limiter { 1. } else { 0. });
+  }
+
+  pub fn process(&mut self, input: T, params: &Params) -> T {
+    self.update_params(params);
+    self.process_sample(input, params, Self::get_mix_gains(params))
+  }
+
+  /// Processes a block of samples. The parameters are checked for changes once per block instead of once per sample.
+  /// Only as many samples as the shortest of the two buffers are processed.
+  pub fn process_block(&mut self, input: &[T], output: &mut [T], params: &Params) {
+    self.update_params(params);
+    let mix_gains = Self::get_mix_gains(params);
+    for (input, output) in input.iter().zip(output.iter_mut()) {
+      *output = self.process_sample(*input, params, mix_gains);
     }
   }
 
-  pub fn initialize_params(&mut self, time: f32, repeats: usize, feedback: f32, skew: f32) {
-    self.repeats[self.active_index].initialize(time, repeats, feedback, skew)
+  /// Processes a block of samples in place. See `process_block`.
+  pub fn process_block_in_place(&mut self, buffer: &mut [T], params: &Params) {
+    self.update_params(params);
+    let mix_gains = Self::get_mix_gains(params);
+    for sample in buffer.iter_mut() {
+      *sample = self.process_sample(*sample, params, mix_gains);
+    }
+  }
+
+  /// Processes the left and right channel through separate delay lines, so the stereo image of the input is preserved in the repeats.
+  /// The repeats are panned according to the spread and spread_mode parameters.
+  pub fn process_stereo(&mut self, input: (T, T), params: &Params) -> (T, T) {
+    self.update_params(params);
+    self.process_stereo_sample(input, params, Self::get_mix_gains(params))
+  }
+
+  /// Processes a block of stereo samples. See `process_block` and `process_stereo`.
+  /// Only as many samples as the shortest of the four buffers are processed.
+  pub fn process_block_stereo(
+    &mut self,
+    input: (&[T], &[T]),
+    output: (&mut [T], &mut [T]),
+    params: &Params,
+  ) {
+    self.update_params(params);
+    let mix_gains = Self::get_mix_gains(params);
+    for (((left, right), output_left), output_right) in input
+      .0
+      .iter()
+      .zip(input.1)
+      .zip(output.0.iter_mut())
+      .zip(output.1.iter_mut())
+    {
+      (*output_left, *output_right) =
+        self.process_stereo_sample((*left, *right), params, mix_gains);
+    }
+  }
+
+  /// Processes a block of stereo samples in place. See `process_block` and `process_stereo`.
+  pub fn process_block_stereo_in_place(&mut self, buffer: (&mut [T], &mut [T]), params: &Params) {
+    self.update_params(params);
+    let mix_gains = Self::get_mix_gains(params);
+    for (left, right) in buffer.0.iter_mut().zip(buffer.1.iter_mut()) {
+      (*left, *right) = self.process_stereo_sample((*left, *right), params, mix_gains);
+    }
+  }
+
+  fn process_sample(&mut self, input: T, params: &Params, mix_gains: (f32, f32)) -> T {
+    let glide_time = self.get_glide_time(params);
+    let repeated = self.repeat(params, glide_time);
+    let repeated = self.remove_dc(repeated, 0, params);
+    let wet = self.limiter.process(repeated, params.limiter);
+    let dry_delay_amount = self.get_dry_delay_amount(params.limiter);
+    let dry = self.get_dry(input, 0, dry_delay_amount);
+    let freeze_amount = self.get_freeze_amount(params.freeze);
+    let feedback = get_feedback(repeated, params, self.get_repeats(params));
+    let time = glide_time.unwrap_or(params.time);
+    self.write(input + feedback, 0, time, freeze_amount);
+
+    let (dry_gain, wet_gain) = self.get_smoothed_mix_gains(mix_gains);
+    dry * T::from_f32(dry_gain) + wet * T::from_f32(wet_gain)
   }
 
-  pub fn process(
+  fn process_stereo_sample(
     &mut self,
-    input: f32,
-    time: f32,
-    repeats: usize,
-    feedback: f32,
-    skew: f32,
-    limiter: bool,
-  ) -> f32 {
-    let repeated = self.repeat(input, time, repeats, feedback, skew);
-    self.delay_line.write(input);
-    self.limiter.process(repeated, limiter)
-  }
-
-  fn crossfade(&mut self, input: f32) -> f32 {
-    let ramp = self.ramp.process();
-    let window = (ramp * FRAC_PI_2).fast_sin();
-    let window = window * window;
-
-    let (window_a, window_b) = if self.active_index == 0 {
-      (window, 1. - window)
+    input: (T, T),
+    params: &Params,
+    mix_gains: (f32, f32),
+  ) -> (T, T) {
+    let glide_time = self.get_glide_time(params);
+    let repeated = self.repeat_stereo(params, glide_time);
+    let repeated = (
+      self.remove_dc(repeated.0, 0, params),
+      self.remove_dc(repeated.1, 1, params),
+    );
+    let wet = self.limiter.process_stereo(repeated, params.limiter);
+    let dry_delay_amount = self.get_dry_delay_amount(params.limiter);
+    let dry = (
+      self.get_dry(input.0, 0, dry_delay_amount),
+      self.get_dry(input.1, 1, dry_delay_amount),
+    );
+    let freeze_amount = self.get_freeze_amount(params.freeze);
+    let repeats = self.get_repeats(params);
+    let feedback = (
+      get_feedback(repeated.0, params, repeats),
+      get_feedback(repeated.1, params, repeats),
+    );
+    let time = glide_time.unwrap_or(params.time);
+    self.write(input.0 + feedback.0, 0, time, freeze_amount);
+    self.write(input.1 + feedback.1, 1, time, freeze_amount);
+
+    let (dry_gain, wet_gain) = self.get_smoothed_mix_gains(mix_gains);
+    let (dry_gain, wet_gain) = (T::from_f32(dry_gain), T::from_f32(wet_gain));
+    (
+      dry.0 * dry_gain + wet.0 * wet_gain,
+      dry.1 * dry_gain + wet.1 * wet_gain,
+    )
+  }
+
+  /// Delays the dry signal by the lookahead time of the limiter, so it stays aligned with the wet signal.
+  /// The delay amount crossfades between the undelayed and the delayed dry signal, so toggling the limiter doesn't click.
+  fn get_dry(&mut self, input: T, channel: usize, delay_amount: f32) -> T {
+    let dry_delay_line = &mut self.dry_delay_lines[channel];
+    let dry = if delay_amount > 0. {
+      input.mix(
+        dry_delay_line.read(self.limiter_latency, Interpolation::Step),
+        delay_amount,
+      )
     } else {
-      (1. - window, window)
+      input
     };
+    dry_delay_line.write(input);
+    dry
+  }
+
+  fn get_repeats(&self, params: &Params) -> usize {
+    tap_layout::get_repeats(params, &self.custom_taps)
+  }
+
+  /// The tube curve adds a DC offset to the repeats, which would otherwise build up in the feedback loop.
+  /// Without drive the repeats aren't saturated, so they pass through untouched.
+  fn remove_dc(&mut self, input: T, channel: usize, params: &Params) -> T {
+    if params.drive > 0. && params.drive_curve == DriveCurve::Tube {
+      self.dc_blockers[channel].process(input)
+    } else {
+      input
+    }
+  }
 
-    let a = self.repeats[0].process(input, &self.delay_line) * window_a;
-    let b = self.repeats[1].process(input, &self.delay_line) * window_b;
-    a + b
+  /// Returns how much of the dry signal is delayed by the lookahead time of the limiter.
+  fn get_dry_delay_amount(&mut self, limiter: bool) -> f32 {
+    if limiter != self.is_dry_delayed {
+      let delay_amount = self.dry_delay_ramps[self.is_dry_delayed as usize].get_value();
+      self.is_dry_delayed = limiter;
+      self.dry_delay_ramps[limiter as usize].start_from(delay_amount);
+    }
+    self.dry_delay_ramps[self.is_dry_delayed as usize].process()
+  }
+
+  /// Returns how much of the looped signal is written back into the delay lines instead of the input.
+  fn get_freeze_amount(&mut self, freeze: bool) -> f32 {
+    if freeze != self.is_frozen {
+      // Take over from where the other ramp is, so toggling freeze halfway a ramp doesn't jump.
+      let freeze_amount = self.freeze_ramps[self.is_frozen as usize].get_value();
+      self.is_frozen = freeze;
+      self.freeze_ramps[freeze as usize].start_from(freeze_amount);
+    }
+    self.freeze_ramps[self.is_frozen as usize].process()
   }
 
-  fn repeat(&mut self, input: f32, time: f32, repeats: usize, feedback: f32, skew: f32) -> f32 {
-    let current_params = Params {
-      repeats,
-      time,
-      feedback,
-      skew,
+  /// While frozen, the signal from one repeat ago is written back into the delay line, so the captured window keeps looping.
+  fn write(&mut self, input: T, channel: usize, time: f32, freeze_amount: f32) {
+    let delay_line = &mut self.delay_lines[channel];
+    let value = if freeze_amount > 0. {
+      input.mix(delay_line.read(time, Interpolation::Step), freeze_amount)
+    } else {
+      input
     };
+    delay_line.write(value);
+  }
+
+  /// Returns the dry and wet gain. A mix value of 0.5 passes both signals at their full level.
+  fn get_mix_gains(params: &Params) -> (f32, f32) {
+    let dry_gain = ((1. - params.mix) * 2.).min(1.) * params.dry;
+    let wet_gain = (params.mix * 2.).min(1.) * params.wet;
+    (dry_gain, wet_gain)
+  }
+
+  fn get_smoothed_mix_gains(&mut self, (dry_gain, wet_gain): (f32, f32)) -> (f32, f32) {
+    (
+      self.dry_gain.process(dry_gain),
+      self.wet_gain.process(wet_gain),
+    )
+  }
+
+  /// Returns the time that the taps glide at in tape mode. In crossfade mode the taps are read at their own time.
+  fn get_glide_time(&mut self, params: &Params) -> Option<f32> {
+    match params.time_mode {
+      TimeMode::Crossfade => {
+        self.glide.initialize(params.time);
+        None
+      }
+      TimeMode::Tape => Some(self.glide.process(params.time, params.glide_rate)),
+    }
+  }
+
+  fn crossfade(&mut self, interpolation: Interpolation, glide_time: Option<f32>) -> T {
+    let gains = self.crossfade.process();
+
+    self
+      .repeats
+      .iter_mut()
+      .zip(gains)
+      .filter(|(_, gain)| *gain > 0.)
+      .map(|(repeats, gain)| {
+        repeats.process(
+          &self.delay_lines[0],
+          &self.lfo,
+          interpolation,
+          self.tap_summation,
+          glide_time,
+        ) * T::from_f32(gain)
+      })
+      .sum()
+  }
 
-    let parameters_have_changed = current_params != self.repeats[self.active_index].get_params();
+  fn crossfade_stereo(&mut self, interpolation: Interpolation, glide_time: Option<f32>) -> (T, T) {
+    let gains = self.crossfade.process();
 
-    match (parameters_have_changed, self.ramp.is_finished()) {
-      (false, true) => self.repeats[self.active_index].process(input, &mut self.delay_line),
-      (true, true) => {
-        self.active_index = self.active_index + 1 & 1;
-        self.repeats[self.active_index].initialize(time, repeats, feedback, skew);
-        self.ramp.start();
-        self.crossfade(input)
+    self
+      .repeats
+      .iter_mut()
+      .zip(gains)
+      .filter(|(_, gain)| *gain > 0.)
+      .map(|(repeats, gain)| {
+        let (left, right) = repeats.process_stereo(
+          &self.delay_lines,
+          &self.lfo,
+          interpolation,
+          self.tap_summation,
+          glide_time,
+        );
+        let gain = T::from_f32(gain);
+        (left * gain, right * gain)
+      })
+      .fold((T::zero(), T::zero()), |sum, (left, right)| {
+        (sum.0 + left, sum.1 + right)
+      })
+  }
+
+  /// Starts crossfading to a newly initialized DelayLineRead instance when the taps have changed.
+  /// A change during a crossfade starts a new crossfade on the third voice, while the other two fade out.
+  /// When all voices are still audible, the change is picked up as soon as one of them is silent,
+  /// so the taps always end up at the latest parameters.
+  /// In tape mode the taps glide to a new time instead. Once the glide has finished, the taps crossfade to taps at the new time,
+  /// which sound the same, but don't need fractional reads anymore.
+  fn update_params(&mut self, params: &Params) {
+    let active_params = self.repeats[self.crossfade.get_active_voice()].get_params();
+    let params = &if params.time_mode == TimeMode::Tape && !self.glide.is_finished(params.time) {
+      Params {
+        time: active_params.time,
+        ..*params
       }
-      _ => self.crossfade(input),
+    } else {
+      *params
+    };
+    if !self.custom_shapes_have_changed && !params.taps_have_changed(&active_params) {
+      return;
+    }
+
+    if let Some(voice) = self.crossfade.get_free_voice() {
+      self.repeats[voice].initialize(params, &self.custom_taps, &self.custom_envelope);
+      self.custom_shapes_have_changed = false;
+      self
+        .crossfade
+        .start(voice, params.crossfade_time, params.crossfade_curve);
+    }
+  }
+
+  fn repeat(&mut self, params: &Params, glide_time: Option<f32>) -> T {
+    self.lfo.process(params);
+    if self.crossfade.is_finished() {
+      self.repeats[self.crossfade.get_active_voice()].process(
+        &self.delay_lines[0],
+        &self.lfo,
+        params.interpolation,
+        self.tap_summation,
+        glide_time,
+      )
+    } else {
+      self.crossfade(params.interpolation, glide_time)
+    }
+  }
+
+  fn repeat_stereo(&mut self, params: &Params, glide_time: Option<f32>) -> (T, T) {
+    self.lfo.process(params);
+    if self.crossfade.is_finished() {
+      self.repeats[self.crossfade.get_active_voice()].process_stereo(
+        &self.delay_lines,
+        &self.lfo,
+        params.interpolation,
+        self.tap_summation,
+        glide_time,
+      )
+    } else {
+      self.crossfade_stereo(params.interpolation, glide_time)
     }
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use super::Params;
+  use super::{
+    CrossfadeCurve, CustomTap, DriveCurve, EnvelopeShape, FeedbackMode, Interpolation, Params,
+    Pattern, Repeat, TapSummation, TimeMode,
+  };
+
+  #[test]
+  fn stereo_output_matches_mono_output_for_identical_channels() {
+    let params = Params {
+      time: 10.,
+      feedback: 0.5,
+      ..Default::default()
+    };
+    let mut mono = Repeat::<f32>::new(1000.);
+    let mut stereo = Repeat::<f32>::new(1000.);
+    mono.initialize_params(&params);
+    stereo.initialize_params(&params);
+
+    for i in 0..100 {
+      let input = if i == 0 { 1. } else { 0. };
+      let mono_output = mono.process(input, &params);
+      let (left_output, right_output) = stereo.process_stereo((input, input), &params);
+      assert_eq!(mono_output, left_output);
+      assert_eq!(mono_output, right_output);
+    }
+  }
+
+  #[test]
+  fn wet_only_output_has_no_dry_signal() {
+    let params = Params {
+      time: 10.,
+      mix: 1.,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+
+    assert_eq!(repeat.process(1., &params), 0.);
+    for _ in 1..10 {
+      assert_eq!(repeat.process(0., &params), 0.);
+    }
+    assert_eq!(repeat.process(0., &params), 1.);
+  }
 
   #[test]
-  fn next_and_previous_parameters_equality() {
+  fn freeze_keeps_looping_the_captured_window() {
+    let params = Params {
+      time: 10.,
+      repeats: 2,
+      mix: 1.,
+      ..Default::default()
+    };
+    let frozen_params = Params {
+      freeze: true,
+      ..params
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+
+    // Fill the delay line with a pattern that repeats every 10 samples, then freeze it.
+    for i in 0..300 {
+      let input = (i % 10) as f32 * 0.1;
+      repeat.process(input, if i < 50 { &params } else { &frozen_params });
+    }
+
+    let output: Vec<f32> = (0..20)
+      .map(|_| repeat.process(0.5, &frozen_params))
+      .collect();
+    for i in 0..10 {
+      assert!((output[i] - output[i + 10]).abs() < 1e-6);
+    }
+    assert_ne!(output[0], output[1]);
+  }
+
+  #[test]
+  fn unfreezing_halfway_the_ramp_fades_back_from_there() {
+    let mut repeat = Repeat::<f32>::new(1000.);
+    for _ in 0..100 {
+      repeat.get_freeze_amount(true);
+    }
+    let freeze_amount = repeat.get_freeze_amount(true);
+    assert!((freeze_amount - 0.505).abs() < 1e-3);
+    assert!((repeat.get_freeze_amount(false) - 0.5).abs() < 1e-3);
+  }
+
+  #[test]
+  fn recirculating_feedback_repeats_beyond_the_last_tap() {
+    let params = Params {
+      time: 10.,
+      repeats: 2,
+      feedback: 0.5,
+      feedback_mode: FeedbackMode::Recirculating,
+      mix: 1.,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+
+    let output: Vec<f32> = (0..31)
+      .map(|i| repeat.process(if i == 0 { 1. } else { 0. }, &params))
+      .collect();
+    assert_eq!(output[10], 1.);
+    assert!(output[20] > 0. && output[20] < 0.5);
+    assert!(output[30] > 0. && output[30] < output[20]);
+  }
+
+  #[test]
+  fn tube_curve_without_drive_leaves_the_repeats_untouched() {
+    let params = Params {
+      time: 10.,
+      repeats: 2,
+      drive_curve: DriveCurve::Tube,
+      mix: 1.,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+
+    let output: Vec<f32> = (0..21)
+      .map(|i| repeat.process(if i == 0 { 1. } else { 0. }, &params))
+      .collect();
+    assert_eq!(output[10], 1.);
+    assert_eq!(output[11], 0.);
+  }
+
+  #[test]
+  fn recirculating_feedback_is_normalized_by_the_custom_taps() {
+    let params = Params {
+      time: 10.,
+      repeats: 16,
+      pattern: Pattern::Custom,
+      feedback: 0.5,
+      feedback_mode: FeedbackMode::Recirculating,
+      mix: 1.,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.set_custom_taps(&[CustomTap { time: 1., gain: 1. }]);
+    repeat.initialize_params(&params);
+
+    let output: Vec<f32> = (0..21)
+      .map(|i| repeat.process(if i == 0 { 1. } else { 0. }, &params))
+      .collect();
+    assert_eq!(output[10], 1.);
+    assert!((output[20] - 0.5_f32.tanh()).abs() < 1e-3);
+  }
+
+  #[test]
+  fn custom_pattern_uses_the_custom_taps() {
+    let params = Params {
+      time: 10.,
+      pattern: Pattern::Custom,
+      mix: 1.,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.set_custom_taps(&[
+      CustomTap {
+        time: 0.5,
+        gain: 1.,
+      },
+      CustomTap {
+        time: 1.5,
+        gain: 0.5,
+      },
+    ]);
+    repeat.initialize_params(&params);
+
+    let output: Vec<f32> = (0..20)
+      .map(|i| repeat.process(if i == 0 { 1. } else { 0. }, &params))
+      .collect();
+    assert_eq!(output[5], 1.);
+    assert_eq!(output[15], 0.5);
+    assert_eq!(output.iter().filter(|x| **x != 0.).count(), 2);
+  }
+
+  #[test]
+  fn invalid_custom_taps_are_clamped_or_left_out() {
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.set_custom_taps(&[
+      CustomTap {
+        time: f32::NAN,
+        gain: 1.,
+      },
+      CustomTap {
+        time: -1.,
+        gain: 0.5,
+      },
+      CustomTap {
+        time: 1.,
+        gain: f32::INFINITY,
+      },
+      CustomTap {
+        time: 2.,
+        gain: -1.,
+      },
+    ]);
     assert!(
-      Params {
-        repeats: 4,
-        time: 200.,
-        feedback: 0.,
-        skew: 0.,
-      } == Params {
-        repeats: 4,
-        time: 200.,
-        feedback: 0.,
-        skew: 0.,
-      }
+      repeat.custom_taps
+        == [
+          CustomTap {
+            time: 0.,
+            gain: 0.5,
+          },
+          CustomTap { time: 2., gain: 0. },
+        ]
     );
+  }
 
-    assert!(
-      Params {
-        repeats: 4,
-        time: 200.,
-        feedback: 0.,
-        skew: 0.,
-      } != Params {
-        repeats: 4,
-        time: 1000.,
-        feedback: 0.,
-        skew: 0.,
+  #[test]
+  fn custom_envelope_shapes_the_repeats() {
+    let params = Params {
+      time: 10.,
+      repeats: 4,
+      envelope_shape: EnvelopeShape::Custom,
+      mix: 1.,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.set_custom_envelope(&[0.25, 1.]);
+    repeat.initialize_params(&params);
+
+    let output: Vec<f32> = (0..31)
+      .map(|i| repeat.process(if i == 0 { 1. } else { 0. }, &params))
+      .collect();
+    assert_eq!(output[10], 0.25);
+    assert_eq!(output[20], 0.625);
+    assert_eq!(output[30], 1.);
+
+    let params = Params {
+      feedback: -1.,
+      ..params
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.set_custom_envelope(&[0.25, 1.]);
+    repeat.initialize_params(&params);
+
+    let output: Vec<f32> = (0..31)
+      .map(|i| repeat.process(if i == 0 { 1. } else { 0. }, &params))
+      .collect();
+    assert_eq!(output[10], 1.);
+    assert_eq!(output[30], 0.25);
+  }
+
+  #[test]
+  fn summed_taps_match_processed_taps() {
+    // At whole sample delay times linear interpolation reads the same samples as step interpolation,
+    // but only step interpolated reads are summed directly.
+    let params = Params {
+      time: 10.,
+      repeats: 12,
+      feedback: 0.8,
+      spread: 0.7,
+      mix: 1.,
+      ..Default::default()
+    };
+    let processed_params = Params {
+      interpolation: Interpolation::Linear,
+      ..params
+    };
+    let mut summed = Repeat::<f32>::new(1000.);
+    let mut scalar = Repeat::<f32>::new(1000.);
+    let mut processed = Repeat::<f32>::new(1000.);
+    scalar.set_tap_summation(TapSummation::Scalar);
+    summed.initialize_params(&params);
+    scalar.initialize_params(&params);
+    processed.initialize_params(&processed_params);
+
+    for i in 0..200 {
+      let input = ((i as f32 * 0.3).sin(), (i as f32 * 0.7).cos());
+      let expected = processed.process_stereo(input, &processed_params);
+      for output in [
+        summed.process_stereo(input, &params),
+        scalar.process_stereo(input, &params),
+      ] {
+        assert!((output.0 - expected.0).abs() < 1e-5);
+        assert!((output.1 - expected.1).abs() < 1e-5);
       }
+    }
+  }
+
+  #[test]
+  fn block_processing_matches_sample_processing() {
+    let params = Params {
+      time: 10.,
+      feedback: 0.5,
+      spread: 0.5,
+      ..Default::default()
+    };
+    let input: Vec<f32> = (0..256).map(|i| (i as f32 * 0.1).sin()).collect();
+    let changed_params = Params {
+      skew: 0.5,
+      ..params
+    };
+
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+    let expected: Vec<f32> = input
+      .iter()
+      .enumerate()
+      .map(|(i, x)| repeat.process(*x, if i < 64 { &params } else { &changed_params }))
+      .collect();
+
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+    let mut output = vec![0.; 256];
+    for (i, (input, output)) in input.chunks(64).zip(output.chunks_mut(64)).enumerate() {
+      repeat.process_block(
+        input,
+        output,
+        if i == 0 { &params } else { &changed_params },
+      );
+    }
+    assert_eq!(output, expected);
+
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+    let expected: Vec<(f32, f32)> = input
+      .iter()
+      .enumerate()
+      .map(|(i, x)| {
+        repeat.process_stereo((*x, -*x), if i < 64 { &params } else { &changed_params })
+      })
+      .collect();
+
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+    let mut left = input.clone();
+    let mut right: Vec<f32> = input.iter().map(|x| -x).collect();
+    for (i, (left, right)) in left.chunks_mut(64).zip(right.chunks_mut(64)).enumerate() {
+      repeat.process_block_stereo_in_place(
+        (left, right),
+        if i == 0 { &params } else { &changed_params },
+      );
+    }
+    assert_eq!(
+      left.into_iter().zip(right).collect::<Vec<(f32, f32)>>(),
+      expected
     );
+  }
 
-    assert!(
-      Params {
-        repeats: 8,
-        time: 200.,
-        feedback: 0.,
-        skew: 0.,
-      } != Params {
-        repeats: 4,
-        time: 1000.,
-        feedback: 0.,
-        skew: 0.,
+  #[test]
+  fn block_processing_stops_at_the_shortest_buffer() {
+    let params = Params::default();
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+
+    let mut output = [2.; 8];
+    repeat.process_block(&[0.; 4], &mut output, &params);
+    assert_eq!(output, [0., 0., 0., 0., 2., 2., 2., 2.]);
+    repeat.process_block(&[0.; 8], &mut output[..4], &params);
+  }
+
+  #[test]
+  fn taps_beyond_the_max_delay_time_are_left_out() {
+    let params = Params {
+      time: 30.,
+      repeats: 4,
+      mix: 1.,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::with_max_delay_time(1000., 50.);
+    repeat.initialize_params(&params);
+
+    let output: Vec<f32> = (0..100)
+      .map(|i| repeat.process(if i == 0 { 1. } else { 0. }, &params))
+      .collect();
+    assert_eq!(output[30], 1.);
+    assert_eq!(output.iter().filter(|x| **x != 0.).count(), 1);
+  }
+
+  #[test]
+  fn f32_and_f64_outputs_agree() {
+    let params = Params {
+      time: 7.3,
+      repeats: 6,
+      feedback: 0.7,
+      spread: 0.5,
+      tone: -0.4,
+      pitch: 3.,
+      drive: 0.3,
+      limiter: true,
+      ..Default::default()
+    };
+    let mut single = Repeat::<f32>::new(1000.);
+    let mut double = Repeat::<f64>::new(1000.);
+    single.initialize_params(&params);
+    double.initialize_params(&params);
+
+    for i in 0..500 {
+      let input = ((i as f32 * 0.3).sin(), (i as f32 * 0.7).cos());
+      let single_output = single.process_stereo(input, &params);
+      let double_output = double.process_stereo((input.0 as f64, input.1 as f64), &params);
+      assert!((single_output.0 as f64 - double_output.0).abs() < 1e-4);
+      assert!((single_output.1 as f64 - double_output.1).abs() < 1e-4);
+    }
+  }
+
+  #[test]
+  fn rapid_parameter_changes_converge_to_the_latest_value() {
+    let params = Params {
+      time: 10.,
+      repeats: 2,
+      mix: 1.,
+      crossfade_time: 50.,
+      crossfade_curve: CrossfadeCurve::EqualPower,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+
+    // Every change arrives before the previous crossfade has finished.
+    for time in [12., 14., 16., 18., 20.] {
+      let params = Params { time, ..params };
+      for _ in 0..20 {
+        repeat.process(0., &params);
       }
-    );
+    }
+    let params = Params {
+      time: 20.,
+      ..params
+    };
+    for _ in 0..200 {
+      repeat.process(0., &params);
+    }
+    assert!(repeat.crossfade.is_finished());
+
+    let output: Vec<f32> = (0..30)
+      .map(|i| repeat.process(if i == 0 { 1. } else { 0. }, &params))
+      .collect();
+    assert_eq!(output[20], 1.);
+    assert_eq!(output.iter().filter(|x| **x != 0.).count(), 1);
+  }
+
+  #[test]
+  fn tape_mode_glides_to_the_new_time() {
+    let params = Params {
+      time: 10.,
+      repeats: 2,
+      mix: 1.,
+      time_mode: TimeMode::Tape,
+      glide_rate: 100.,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+
+    // A constant input is delayed at a time that keeps changing, so the output follows the glide.
+    let params = Params {
+      time: 20.,
+      ..params
+    };
+    let output: Vec<f32> = (0..50).map(|i| repeat.process(i as f32, &params)).collect();
+    assert!(repeat.crossfade.is_finished());
+    // A rising input that's read at a growing delay time rises slower, which is the pitch bend of the tape.
+    let slope = output[40] - output[39];
+    assert!(slope > 0.85 && slope < 0.95, "{slope}");
+
+    for _ in 0..100 {
+      repeat.process(0., &params);
+    }
+    let output: Vec<f32> = (0..30)
+      .map(|i| repeat.process(if i == 0 { 1. } else { 0. }, &params))
+      .collect();
+    assert_eq!(output[20], 1.);
+    assert_eq!(output.iter().filter(|x| **x != 0.).count(), 1);
+  }
+
+  #[test]
+  fn dry_signal_is_latency_compensated_when_limiter_is_on() {
+    let params = Params {
+      mix: 0.,
+      limiter: true,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+    let latency = repeat.limiter.get_latency();
+
+    assert_eq!(repeat.process(1., &params), 0.);
+    for _ in 1..latency {
+      assert_eq!(repeat.process(0., &params), 0.);
+    }
+    assert_eq!(repeat.process(0., &params), 1.);
+  }
+
+  #[test]
+  fn toggling_the_limiter_crossfades_the_dry_delay() {
+    let params = Params {
+      mix: 0.,
+      limiter: true,
+      ..Default::default()
+    };
+    let mut repeat = Repeat::<f32>::new(1000.);
+    repeat.initialize_params(&params);
+    let latency = repeat.limiter.get_latency() as f32;
+    assert!(latency > 1.);
+
+    // A rising input shows the delay as an offset, which would jump by the latency without a crossfade.
+    let mut previous_output = 0.;
+    for i in 0..20 {
+      previous_output = repeat.process(i as f32, &params);
+    }
+    assert_eq!(previous_output, 19. - latency);
+
+    let params = Params {
+      limiter: false,
+      ..params
+    };
+    for i in 20..100 {
+      let output = repeat.process(i as f32, &params);
+      assert!(output - previous_output <= 1. + latency * 0.05);
+      previous_output = output;
+    }
+    assert_eq!(previous_output, 99.);
   }
 }
@@ -1,16 +1,22 @@
 mod moving_min;
 mod ramp_slide;
-use {moving_min::MovingMin, ramp_slide::RampSlide};
+use {
+  crate::shared::sample::Sample,
+  alloc::{vec, vec::Vec},
+  moving_min::MovingMin,
+  ramp_slide::RampSlide,
+};
 
-pub struct Limiter {
-  buffer: Vec<f32>,
+/// The gain reduction is calculated in `f32`, the delayed signal is kept in the sample type.
+pub struct Limiter<T> {
+  buffer: Vec<(T, T)>,
   buffer_index: usize,
   slide: RampSlide,
   limit: f32,
   moving_min: MovingMin,
 }
 
-impl Limiter {
+impl<T: Sample> Limiter<T> {
   pub fn new(
     sample_rate: f32,
     attack_time: f32,
@@ -21,7 +27,7 @@ impl Limiter {
     let buffer_length = (attack_time * 0.001 * sample_rate) as usize;
 
     Self {
-      buffer: vec![0.; buffer_length],
+      buffer: vec![(T::zero(), T::zero()); buffer_length],
       buffer_index: 0,
       slide: RampSlide::new(sample_rate, release_time, attack_time),
       limit,
@@ -29,18 +35,37 @@ impl Limiter {
     }
   }
 
-  pub fn process(&mut self, input: f32, is_on: bool) -> f32 {
+  pub fn process(&mut self, input: T, is_on: bool) -> T {
     if is_on {
-      let limiter_gain = self.get_limiter_gain(input);
+      let limiter_gain = self.get_limiter_gain(input.as_f32());
+      self.write_to_buffer((input, T::zero()));
+      let (delay_output, _) = self.read_from_buffer();
+
+      delay_output * T::from_f32(limiter_gain)
+    } else {
+      input
+    }
+  }
+
+  /// The gain reduction is linked between both channels, so the stereo image stays intact.
+  pub fn process_stereo(&mut self, input: (T, T), is_on: bool) -> (T, T) {
+    if is_on {
+      let limiter_gain = self.get_limiter_gain(input.0.abs().max(input.1.abs()).as_f32());
       self.write_to_buffer(input);
-      let delay_output = self.read_from_buffer();
+      let (left_output, right_output) = self.read_from_buffer();
+      let limiter_gain = T::from_f32(limiter_gain);
 
-      delay_output * limiter_gain
+      (left_output * limiter_gain, right_output * limiter_gain)
     } else {
       input
     }
   }
 
+  /// Returns the lookahead time of the limiter in samples.
+  pub fn get_latency(&self) -> usize {
+    self.buffer.len()
+  }
+
   fn get_limiter_gain(&mut self, input: f32) -> f32 {
     let gain_reduction = self.get_gain_reduction(input);
     let moving_min = self.moving_min.process(gain_reduction);
@@ -57,7 +82,7 @@ impl Limiter {
     }
   }
 
-  fn read_from_buffer(&self) -> f32 {
+  fn read_from_buffer(&self) -> (T, T) {
     self.buffer[self.buffer_index]
   }
 
@@ -70,7 +95,7 @@ impl Limiter {
     }
   }
 
-  fn write_to_buffer(&mut self, input: f32) {
+  fn write_to_buffer(&mut self, input: (T, T)) {
     self.buffer[self.buffer_index] = input;
     self.buffer_index = self.wrap(self.buffer_index + 1);
   }
@@ -0,0 +1,200 @@
+use crate::{
+  crossfade::CrossfadeCurve, delay_line::Interpolation, drive::DriveCurve, envelope::EnvelopeShape,
+  feedback::FeedbackMode, glide::TimeMode, lfo::LfoShape, pattern::Pattern,
+  pitch_shifter::ReverseMode, spread::SpreadMode,
+};
+
+#[derive(PartialEq, Clone, Copy)]
+pub struct Params {
+  /// The time between repeats in milliseconds.
+  pub time: f32,
+  /// How changes of the time are applied to the taps.
+  pub time_mode: TimeMode,
+  /// The maximum rate at which the time glides in tape mode in milliseconds per second.
+  pub glide_rate: f32,
+  pub repeats: usize,
+  pub feedback: f32,
+  pub feedback_mode: FeedbackMode,
+  /// The shape of the gains over the repeats. Negative feedback reverses the shape.
+  pub envelope_shape: EnvelopeShape,
+  pub skew: f32,
+  pub pattern: Pattern,
+  /// The amount of swing or random jitter between 0 and 1.
+  pub pattern_amount: f32,
+  /// The number of steps of the grid over which the euclidean pattern spreads the taps.
+  pub euclidean_steps: usize,
+  pub pattern_seed: u32,
+  /// The amount of stereo spread between 0 and 1.
+  pub spread: f32,
+  pub spread_mode: SpreadMode,
+  /// The tone of the repeats between -1 and 1. Negative values darken successive repeats with a lowpass filter,
+  /// positive values thin them out with a highpass filter.
+  pub tone: f32,
+  /// Bends the progression of the tone over the repeats between -1 and 1.
+  pub tone_skew: f32,
+  /// The pitch interval in semitones that's added with every repeat.
+  pub pitch: f32,
+  pub reverse_mode: ReverseMode,
+  pub lfo_shape: LfoShape,
+  /// The rate of the time modulation in Hz.
+  pub lfo_rate: f32,
+  /// The depth of the time modulation in milliseconds.
+  pub lfo_depth: f32,
+  /// The phase offset between successive taps in cycles.
+  pub lfo_phase_offset: f32,
+  /// The interpolation of the tap reads. Modulated and pitch shifted reads use linear interpolation instead of step interpolation.
+  pub interpolation: Interpolation,
+  /// The time in milliseconds of the crossfade from the old taps to the new taps when the taps change.
+  pub crossfade_time: f32,
+  pub crossfade_curve: CrossfadeCurve,
+  /// The amount of saturation of every tap between 0 and 1.
+  pub drive: f32,
+  pub drive_curve: DriveCurve,
+  pub limiter: bool,
+  /// Stops writing the input into the delay line and keeps looping the last repeat.
+  pub freeze: bool,
+  /// The balance between the dry and wet signal between 0 and 1.
+  pub mix: f32,
+  /// The gain of the dry signal as a linear amplitude.
+  pub dry: f32,
+  /// The gain of the wet signal as a linear amplitude.
+  pub wet: f32,
+}
+
+impl Params {
+  /// Returns true if any of the parameters that shape the taps has changed.
+  pub(crate) fn taps_have_changed(&self, other: &Params) -> bool {
+    self.time != other.time
+      || self.repeats != other.repeats
+      || self.feedback != other.feedback
+      || self.feedback_mode != other.feedback_mode
+      || self.envelope_shape != other.envelope_shape
+      || self.skew != other.skew
+      || self.pattern != other.pattern
+      || self.pattern_amount != other.pattern_amount
+      || self.euclidean_steps != other.euclidean_steps
+      || self.pattern_seed != other.pattern_seed
+      || self.spread != other.spread
+      || self.spread_mode != other.spread_mode
+      || self.tone != other.tone
+      || self.tone_skew != other.tone_skew
+      || self.pitch != other.pitch
+      || self.reverse_mode != other.reverse_mode
+      || self.drive != other.drive
+      || self.drive_curve != other.drive_curve
+  }
+}
+
+impl Default for Params {
+  fn default() -> Self {
+    Self {
+      time: 250.,
+      time_mode: TimeMode::Crossfade,
+      glide_rate: 200.,
+      repeats: 4,
+      feedback: 1.,
+      feedback_mode: FeedbackMode::Simulated,
+      envelope_shape: EnvelopeShape::Exponential,
+      skew: 0.,
+      pattern: Pattern::Skew,
+      pattern_amount: 0.,
+      euclidean_steps: 8,
+      pattern_seed: 0,
+      spread: 0.,
+      spread_mode: SpreadMode::PingPong,
+      tone: 0.,
+      tone_skew: 0.,
+      pitch: 0.,
+      reverse_mode: ReverseMode::Off,
+      lfo_shape: LfoShape::Sine,
+      lfo_rate: 1.,
+      lfo_depth: 0.,
+      lfo_phase_offset: 0.,
+      interpolation: Interpolation::Step,
+      crossfade_time: 200.,
+      crossfade_curve: CrossfadeCurve::Smooth,
+      drive: 0.,
+      drive_curve: DriveCurve::Tanh,
+      limiter: false,
+      freeze: false,
+      mix: 0.5,
+      dry: 1.,
+      wet: 1.,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Params;
+
+  #[test]
+  fn next_and_previous_parameters_equality() {
+    assert!(
+      Params {
+        repeats: 4,
+        time: 200.,
+        feedback: 0.,
+        skew: 0.,
+        ..Default::default()
+      } == Params {
+        repeats: 4,
+        time: 200.,
+        feedback: 0.,
+        skew: 0.,
+        ..Default::default()
+      }
+    );
+
+    assert!(
+      Params {
+        repeats: 4,
+        time: 200.,
+        feedback: 0.,
+        skew: 0.,
+        ..Default::default()
+      } != Params {
+        repeats: 4,
+        time: 1000.,
+        feedback: 0.,
+        skew: 0.,
+        ..Default::default()
+      }
+    );
+
+    assert!(
+      Params {
+        repeats: 8,
+        time: 200.,
+        feedback: 0.,
+        skew: 0.,
+        ..Default::default()
+      } != Params {
+        repeats: 4,
+        time: 1000.,
+        feedback: 0.,
+        skew: 0.,
+        ..Default::default()
+      }
+    );
+  }
+
+  #[test]
+  fn taps_have_changed() {
+    let params = Params::default();
+
+    assert!(!params.taps_have_changed(&Params {
+      limiter: true,
+      ..params
+    }));
+    assert!(!params.taps_have_changed(&Params { mix: 1., ..params }));
+    assert!(params.taps_have_changed(&Params {
+      spread: 0.5,
+      ..params
+    }));
+    assert!(params.taps_have_changed(&Params {
+      tone: -0.5,
+      ..params
+    }));
+  }
+}
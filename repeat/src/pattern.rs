@@ -0,0 +1,187 @@
+use crate::{
+  shared::random::{hash, random},
+  Params,
+};
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+const GOLDEN_RATIO: f32 = 1.618034;
+/// The average interval of the golden pattern, so it spans the same time as evenly spaced taps.
+const GOLDEN_AVERAGE_INTERVAL: f32 = 1. + (GOLDEN_RATIO - 1.) / GOLDEN_RATIO;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Pattern {
+  /// Evenly spaced taps, or accelerating and decelerating taps depending on the skew parameter.
+  Skew,
+  /// Spreads the taps as evenly as possible over a grid of steps.
+  Euclidean,
+  /// Delays every other tap by the pattern amount.
+  Swing,
+  /// Alternates long and short intervals according to the golden ratio.
+  Golden,
+  /// Moves every tap by a seeded random amount.
+  Random,
+  /// Uses the taps set with `Repeat::set_custom_taps`.
+  Custom,
+}
+
+/// A user defined tap. The time is a multiple of the repeat time, so custom patterns follow the tempo.
+#[derive(PartialEq, Clone, Copy)]
+pub struct CustomTap {
+  pub time: f32,
+  pub gain: f32,
+}
+
+impl CustomTap {
+  /// Returns the tap with a negative time or gain clamped to zero, or None when either isn't finite.
+  pub fn validate(&self) -> Option<Self> {
+    if self.time.is_finite() && self.gain.is_finite() {
+      Some(Self {
+        time: self.time.max(0.),
+        gain: self.gain.max(0.),
+      })
+    } else {
+      None
+    }
+  }
+}
+
+/// Returns the position of the tap at the given index as a multiple of the repeat time.
+/// The skew and custom patterns are handled by `DelayLineRead`.
+pub fn get_position(index: usize, taps: usize, params: &Params) -> f32 {
+  match params.pattern {
+    Pattern::Euclidean => get_euclidean_position(index, taps, params.euclidean_steps),
+    Pattern::Swing => get_swing_position(index, params.pattern_amount),
+    Pattern::Golden => get_golden_position(index),
+    Pattern::Random => get_random_position(index, params.pattern_amount, params.pattern_seed),
+    Pattern::Skew | Pattern::Custom => index as f32,
+  }
+}
+
+/// Distributes the pulses over the steps with the Bresenham approach of the Bjorklund algorithm.
+/// There are at least as many steps as there are taps.
+fn get_euclidean_position(index: usize, taps: usize, steps: usize) -> f32 {
+  let steps = steps.max(taps);
+  (0..steps)
+    .filter(|step| step * taps % steps < taps)
+    .nth(index - 1)
+    .map_or(index, |step| step + 1) as f32
+}
+
+fn get_swing_position(index: usize, amount: f32) -> f32 {
+  if index % 2 == 1 {
+    index as f32 + amount * 0.5
+  } else {
+    index as f32
+  }
+}
+
+/// Sums the intervals of the Fibonacci word, in which long intervals are the golden ratio times as long as short ones.
+fn get_golden_position(index: usize) -> f32 {
+  (1..=index)
+    .map(|k| {
+      let is_long =
+        ((k + 1) as f32 / GOLDEN_RATIO).floor() - (k as f32 / GOLDEN_RATIO).floor() == 1.;
+      if is_long {
+        GOLDEN_RATIO
+      } else {
+        1.
+      }
+    })
+    .sum::<f32>()
+    / GOLDEN_AVERAGE_INTERVAL
+}
+
+/// The jitter stays within half the repeat time, so the taps never swap places.
+fn get_random_position(index: usize, amount: f32, seed: u32) -> f32 {
+  let jitter = random(hash(seed).wrapping_add(index as u32)) * 2. - 1.;
+  index as f32 + jitter * amount * 0.5
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{get_position, Pattern};
+  use crate::Params;
+
+  fn get_positions(taps: usize, pattern: Pattern, amount: f32, steps: usize) -> Vec<f32> {
+    get_seeded_positions(taps, pattern, amount, steps, Params::default().pattern_seed)
+  }
+
+  fn get_seeded_positions(
+    taps: usize,
+    pattern: Pattern,
+    amount: f32,
+    steps: usize,
+    seed: u32,
+  ) -> Vec<f32> {
+    let params = Params {
+      pattern,
+      pattern_amount: amount,
+      euclidean_steps: steps,
+      pattern_seed: seed,
+      ..Default::default()
+    };
+    (1..=taps)
+      .map(|index| get_position(index, taps, &params))
+      .collect()
+  }
+
+  #[test]
+  fn euclidean() {
+    assert_eq!(
+      get_positions(3, Pattern::Euclidean, 0., 8),
+      vec![1., 4., 7.]
+    );
+    assert_eq!(
+      get_positions(5, Pattern::Euclidean, 0., 8),
+      vec![1., 3., 5., 6., 8.]
+    );
+    assert_eq!(
+      get_positions(4, Pattern::Euclidean, 0., 2),
+      vec![1., 2., 3., 4.]
+    );
+  }
+
+  #[test]
+  fn swing() {
+    assert_eq!(
+      get_positions(4, Pattern::Swing, 0.5, 0),
+      vec![1.25, 2., 3.25, 4.]
+    );
+  }
+
+  #[test]
+  fn golden() {
+    let positions = get_positions(16, Pattern::Golden, 0., 0);
+    let intervals: Vec<f32> = positions.windows(2).map(|w| w[1] - w[0]).collect();
+    let shortest = intervals.iter().cloned().fold(f32::MAX, f32::min);
+    let longest = intervals.iter().cloned().fold(0., f32::max);
+    assert!((longest / shortest - 1.618034).abs() < 1e-4);
+    assert!((positions[15] - 16.).abs() < 1.);
+  }
+
+  #[test]
+  fn random_jitter_keeps_the_order_of_the_taps() {
+    let positions = get_positions(16, Pattern::Random, 1., 0);
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(positions, get_positions(16, Pattern::Random, 1., 0));
+    assert_eq!(
+      get_positions(4, Pattern::Random, 0., 0),
+      vec![1., 2., 3., 4.]
+    );
+  }
+
+  #[test]
+  fn neighbouring_seeds_have_unrelated_jitter() {
+    let jitter = |seed| -> Vec<f32> {
+      get_seeded_positions(16, Pattern::Random, 1., 0, seed)
+        .iter()
+        .enumerate()
+        .map(|(index, position)| position - (index + 1) as f32)
+        .collect()
+    };
+    let first = jitter(1);
+    let second = jitter(2);
+    assert_ne!(first[1..], second[..15]);
+  }
+}
@@ -0,0 +1,192 @@
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+use {
+  crate::{
+    delay_line::{DelayLine, Interpolation},
+    shared::sample::Sample,
+  },
+  core::f32::consts::PI,
+};
+
+/// The length of the window in which the two read heads sweep through the delay line in milliseconds.
+const WINDOW: f32 = 50.;
+const MAX_PITCH: f32 = 48.;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum ReverseMode {
+  Off,
+  All,
+  Alternate,
+}
+
+impl ReverseMode {
+  /// Returns true if the tap at the given index plays backwards. In alternate mode the first repeat is reversed.
+  pub fn is_reversed(&self, index: usize) -> bool {
+    match self {
+      ReverseMode::Off => false,
+      ReverseMode::All => true,
+      ReverseMode::Alternate => index % 2 == 1,
+    }
+  }
+}
+
+/// A dual-head pitch shifter for a single tap. The two heads sweep through the window half a cycle apart,
+/// so one head fades in while the other one jumps back.
+#[derive(Clone, Copy)]
+pub struct PitchShifter {
+  phase: f32,
+  phase_step: f32,
+  window: f32,
+  is_bypassed: bool,
+}
+
+impl PitchShifter {
+  /// The pitch in semitones accumulates with every tap, so the repeats become a pitch cascade.
+  /// A reversed tap reads backwards through chunks of the given time in milliseconds.
+  pub fn new(sample_rate: f32, index: usize, pitch: f32, time: f32, reverse: bool) -> Self {
+    let ratio = Self::get_ratio(index, pitch);
+    let (ratio, window) = if reverse {
+      (-ratio, time * 2.)
+    } else {
+      (ratio, WINDOW)
+    };
+
+    Self {
+      phase: 0.,
+      phase_step: (1. - ratio) * 1000. / (window * sample_rate),
+      window,
+      is_bypassed: pitch == 0. && !reverse,
+    }
+  }
+
+  /// The interpolation is used when the pitch shifter is bypassed. Shifted reads always interpolate between samples.
+  pub fn is_bypassed(&self) -> bool {
+    self.is_bypassed
+  }
+
+  pub fn process<T: Sample>(
+    &mut self,
+    delay_line: &DelayLine<T>,
+    time: f32,
+    interpolation: Interpolation,
+  ) -> T {
+    if self.is_bypassed {
+      return delay_line.read(time, interpolation);
+    }
+
+    let interpolation = interpolation.to_fractional();
+    let heads = self.get_heads();
+    self.advance();
+    heads
+      .iter()
+      .map(|(offset, gain)| delay_line.read(time + offset, interpolation) * T::from_f32(*gain))
+      .sum()
+  }
+
+  pub fn process_stereo<T: Sample>(
+    &mut self,
+    delay_lines: &[DelayLine<T>; 2],
+    time: f32,
+    interpolation: Interpolation,
+  ) -> (T, T) {
+    if self.is_bypassed {
+      return (
+        delay_lines[0].read(time, interpolation),
+        delay_lines[1].read(time, interpolation),
+      );
+    }
+
+    let interpolation = interpolation.to_fractional();
+    let heads = self.get_heads();
+    self.advance();
+    heads
+      .iter()
+      .map(|(offset, gain)| {
+        let gain = T::from_f32(*gain);
+        (
+          delay_lines[0].read(time + offset, interpolation) * gain,
+          delay_lines[1].read(time + offset, interpolation) * gain,
+        )
+      })
+      .fold((T::zero(), T::zero()), |sum, (left, right)| {
+        (sum.0 + left, sum.1 + right)
+      })
+  }
+
+  fn get_ratio(index: usize, pitch: f32) -> f32 {
+    let semitones = (pitch * index as f32).clamp(-MAX_PITCH, MAX_PITCH);
+    2_f32.powf(semitones / 12.)
+  }
+
+  /// Returns the offset in milliseconds and the gain of both read heads. The sin² windows of the heads add up to one.
+  fn get_heads(&self) -> [(f32, f32); 2] {
+    [self.phase, (self.phase + 0.5).fract()].map(|phase| {
+      let window = (phase * PI).sin();
+      (phase * self.window, window * window)
+    })
+  }
+
+  fn advance(&mut self) {
+    let phase = self.phase + self.phase_step;
+    self.phase = phase - phase.floor();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{PitchShifter, ReverseMode};
+
+  fn assert_approximately_eq(left: f32, right: f32) {
+    assert!((left - right).abs() < 1e-4, "left: {left}, right: {right}");
+  }
+
+  #[test]
+  fn pitch_accumulates_per_tap() {
+    assert_approximately_eq(PitchShifter::get_ratio(1, 12.), 2.);
+    assert_approximately_eq(PitchShifter::get_ratio(2, 12.), 4.);
+    assert_approximately_eq(PitchShifter::get_ratio(2, -12.), 0.25);
+    assert_approximately_eq(PitchShifter::get_ratio(8, 12.), 16.);
+  }
+
+  #[test]
+  fn head_windows_add_up_to_one() {
+    let mut pitch_shifter = PitchShifter::new(1000., 1, 7., 100., false);
+    for _ in 0..100 {
+      let [(_, a), (_, b)] = pitch_shifter.get_heads();
+      assert_approximately_eq(a + b, 1.);
+      pitch_shifter.advance();
+    }
+  }
+
+  #[test]
+  fn heads_move_towards_the_write_pointer_when_pitching_up() {
+    let mut pitch_shifter = PitchShifter::new(1000., 1, 12., 100., false);
+    pitch_shifter.phase = 0.5;
+    let [(offset, _), _] = pitch_shifter.get_heads();
+    pitch_shifter.advance();
+    let [(next_offset, _), _] = pitch_shifter.get_heads();
+    // Reading at twice the speed means the offset shrinks by one sample, which is one millisecond here.
+    assert_approximately_eq(offset - next_offset, 1.);
+  }
+
+  #[test]
+  fn heads_move_away_from_the_write_pointer_when_reversed() {
+    let mut pitch_shifter = PitchShifter::new(1000., 1, 0., 100., true);
+    pitch_shifter.phase = 0.25;
+    let [(offset, _), _] = pitch_shifter.get_heads();
+    pitch_shifter.advance();
+    let [(next_offset, _), _] = pitch_shifter.get_heads();
+    // Reading backwards at the original speed means the offset grows by two samples.
+    assert_approximately_eq(next_offset - offset, 2.);
+    assert_approximately_eq(pitch_shifter.window, 200.);
+  }
+
+  #[test]
+  fn reverse_modes() {
+    assert!(!ReverseMode::Off.is_reversed(1));
+    assert!(ReverseMode::All.is_reversed(1));
+    assert!(ReverseMode::All.is_reversed(2));
+    assert!(ReverseMode::Alternate.is_reversed(1));
+    assert!(!ReverseMode::Alternate.is_reversed(2));
+  }
+}
@@ -6,6 +6,7 @@ pub struct Ramp {
 }
 
 impl Ramp {
+  /// A negative frequency makes the ramp run backwards from one to zero.
   pub fn new(sample_rate: f32, freq: f32) -> Self {
     Self {
       x: 0.,
@@ -20,15 +21,29 @@ impl Ramp {
     self.is_active = true;
   }
 
+  /// Starts the ramp at the given position instead of at its start, so it can take over from another ramp without a jump.
+  pub fn start_from(&mut self, x: f32) {
+    self.x = x;
+    self.trigger = false;
+    self.is_active = true;
+  }
+
+  pub fn get_value(&self) -> f32 {
+    self.x
+  }
+
   pub fn process(&mut self) -> f32 {
     if self.trigger {
-      self.x = 0.;
+      self.x = if self.step_size < 0. { 1. } else { 0. };
       self.trigger = false;
     } else if self.is_active {
       self.x += self.step_size;
       if self.x >= 1. {
         self.x = 1.;
         self.is_active = false;
+      } else if self.x <= 0. {
+        self.x = 0.;
+        self.is_active = false;
       }
     }
 
@@ -84,6 +99,17 @@ mod tests {
     assert_approximately_eq(ramp.process(), 0.);
   }
 
+  #[test]
+  fn start_from_a_position() {
+    let mut ramp = Ramp::new(10., -1.);
+    ramp.start_from(0.35);
+    assert_approximately_eq(ramp.process(), 0.25);
+    assert_approximately_eq(ramp.process(), 0.15);
+    assert_approximately_eq(ramp.process(), 0.05);
+    assert_approximately_eq(ramp.process(), 0.);
+    assert!(ramp.is_finished());
+  }
+
   #[test]
   fn is_finished() {
     let mut ramp = Ramp::new(10., 1.);
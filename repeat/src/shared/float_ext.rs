@@ -1,4 +1,6 @@
-use std::f32::consts::{FRAC_PI_2, PI};
+use core::f32::consts::{FRAC_PI_2, PI};
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
 
 pub trait FloatExt {
   fn dbtoa(self) -> Self;
@@ -13,8 +15,6 @@ pub trait FloatExt {
   fn fast_cos(self) -> Self;
   fn fast_sin_bhaskara(self) -> Self;
   fn fast_cos_bhaskara(self) -> Self;
-  fn fast_pow(self, exponent: Self) -> Self;
-  fn fast_exp(self) -> Self;
   fn mstosamps(self, sample_rate: Self) -> Self;
 }
 
@@ -119,15 +119,6 @@ impl FloatExt for f32 {
     (pi_squared - 4. * x_squared) / (pi_squared + x_squared)
   }
 
-  fn fast_pow(self, exponent: Self) -> Self {
-    pow2(exponent * log2(self))
-  }
-
-  /// Exponential function.
-  fn fast_exp(self) -> Self {
-    pow2(1.442695040_f32 * self)
-  }
-
   /// Convert milliseconds to samples based on the samplerate.
   fn mstosamps(self, sample_rate: Self) -> Self {
     self * 0.001 * sample_rate
@@ -247,18 +238,6 @@ mod tests {
   }
 }
 
-fn log2(x: f32) -> f32 {
-  let mut y = x.to_bits() as f32;
-  y *= 1.1920928955078125e-7_f32;
-  y - 126.94269504_f32
-}
-
-fn pow2(p: f32) -> f32 {
-  let clipp = if p < -126.0 { -126.0_f32 } else { p };
-  let v = ((1 << 23) as f32 * (clipp + 126.94269504_f32)) as u32;
-  f32::from_bits(v)
-}
-
 fn sin_approx(x: f32) -> f32 {
   const FOUROVERPI: f32 = 1.2732395447351627;
   const FOUROVERPISQ: f32 = 0.40528473456935109;
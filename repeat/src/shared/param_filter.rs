@@ -0,0 +1,45 @@
+use core::f32::consts::TAU;
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+/// A one-pole lowpass filter to smooth out parameter changes.
+pub struct ParamFilter {
+  z: f32,
+  b1: f32,
+}
+
+impl ParamFilter {
+  pub fn new(sample_rate: f32, freq: f32) -> Self {
+    Self {
+      z: 0.,
+      b1: (-TAU * freq / sample_rate).exp(),
+    }
+  }
+
+  pub fn initialize(&mut self, value: f32) {
+    self.z = value;
+  }
+
+  pub fn process(&mut self, input: f32) -> f32 {
+    self.z = input + (self.z - input) * self.b1;
+    self.z
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ParamFilter;
+
+  #[test]
+  fn should_converge_to_input() {
+    let mut param_filter = ParamFilter::new(1000., 10.);
+    param_filter.initialize(0.);
+
+    let first_output = param_filter.process(1.);
+    assert!(first_output > 0. && first_output < 1.);
+    for _ in 0..1000 {
+      param_filter.process(1.);
+    }
+    assert!((param_filter.process(1.) - 1.).abs() < 1e-6);
+  }
+}
@@ -0,0 +1,36 @@
+/// Returns a deterministic pseudo random value between 0 and 1 for the given seed.
+/// The same seed always returns the same value, so random tap layouts can be recalled.
+pub fn random(seed: u32) -> f32 {
+  (hash(seed) >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Scrambles the bits of the seed, so neighbouring seeds give unrelated values.
+/// Hash a seed before adding an index to it, otherwise the sequences of neighbouring seeds are shifted copies.
+pub fn hash(seed: u32) -> u32 {
+  let mut x = seed.wrapping_mul(0x9e3779b9) ^ 0x85ebca6b;
+  x ^= x >> 16;
+  x = x.wrapping_mul(0x7feb352d);
+  x ^= x >> 15;
+  x = x.wrapping_mul(0x846ca68b);
+  x ^= x >> 16;
+  x
+}
+
+#[cfg(test)]
+mod tests {
+  use super::random;
+
+  #[test]
+  fn should_be_deterministic() {
+    assert_eq!(random(1), random(1));
+    assert_ne!(random(1), random(2));
+  }
+
+  #[test]
+  fn should_stay_within_range() {
+    for seed in 0..1000 {
+      let value = random(seed);
+      assert!((0. ..1.).contains(&value));
+    }
+  }
+}
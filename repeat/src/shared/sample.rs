@@ -0,0 +1,42 @@
+use {core::iter::Sum, num_traits::Float};
+
+/// The floating point type of the audio signal. The parameters and the control signals, like ramps and gains, stay `f32`.
+pub trait Sample: Float + Sum + Default + Send + Sync + 'static {
+  fn from_f32(value: f32) -> Self;
+
+  fn as_f32(self) -> f32;
+
+  /// Crossfades between self and other, like `FloatExt::mix`.
+  fn mix(self, other: Self, factor: f32) -> Self {
+    self + (other - self) * Self::from_f32(factor)
+  }
+
+  /// The same tanh approximation as `FloatExt::fast_tanh2`.
+  fn fast_tanh(self) -> Self {
+    let x2 = self * self;
+    let x3 = x2 * self;
+    let x4 = x3 * self;
+    (Self::from_f32(105.) * self + Self::from_f32(10.) * x3)
+      / (Self::from_f32(105.) + Self::from_f32(45.) * x2 + x4)
+  }
+}
+
+impl Sample for f32 {
+  fn from_f32(value: f32) -> Self {
+    value
+  }
+
+  fn as_f32(self) -> f32 {
+    self
+  }
+}
+
+impl Sample for f64 {
+  fn from_f32(value: f32) -> Self {
+    value as f64
+  }
+
+  fn as_f32(self) -> f32 {
+    self as f32
+  }
+}
@@ -0,0 +1,119 @@
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+use {
+  crate::shared::random::random,
+  core::f32::consts::{FRAC_PI_4, SQRT_2},
+};
+
+const RANDOM_SEED: u32 = 0x5eed;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum SpreadMode {
+  PingPong,
+  Linear,
+  Random,
+  CenterOut,
+}
+
+/// Returns the pan position between -1 (left) and 1 (right) for the repeat at the given index.
+/// The first tap is the dry signal, so it always stays centered.
+pub fn get_pan(index: usize, repeats: usize, spread: f32, spread_mode: SpreadMode) -> f32 {
+  if index == 0 {
+    return 0.;
+  }
+
+  let pan = match spread_mode {
+    SpreadMode::PingPong => {
+      if index % 2 == 1 {
+        -1.
+      } else {
+        1.
+      }
+    }
+    SpreadMode::Linear => {
+      if repeats > 2 {
+        (index - 1) as f32 / (repeats - 2) as f32 * 2. - 1.
+      } else {
+        0.
+      }
+    }
+    SpreadMode::Random => random(RANDOM_SEED + index as u32) * 2. - 1.,
+    SpreadMode::CenterOut => {
+      let distance = index as f32 / (repeats - 1) as f32;
+      if index % 2 == 1 {
+        -distance
+      } else {
+        distance
+      }
+    }
+  };
+
+  pan * spread
+}
+
+/// Returns the constant power gains for the left and right channel. A centered pan position returns unity gain for both channels.
+pub fn get_pan_gains(pan: f32) -> (f32, f32) {
+  let angle = (pan + 1.) * FRAC_PI_4;
+  (angle.cos() * SQRT_2, angle.sin() * SQRT_2)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{get_pan, get_pan_gains, SpreadMode};
+
+  fn assert_approximately_eq(left: f32, right: f32) {
+    assert!((left - right).abs() < 1e-6, "left: {left}, right: {right}");
+  }
+
+  #[test]
+  fn dry_tap_stays_centered() {
+    assert_eq!(get_pan(0, 4, 1., SpreadMode::PingPong), 0.);
+    assert_eq!(get_pan(0, 4, 1., SpreadMode::Linear), 0.);
+    assert_eq!(get_pan(0, 4, 1., SpreadMode::Random), 0.);
+    assert_eq!(get_pan(0, 4, 1., SpreadMode::CenterOut), 0.);
+  }
+
+  #[test]
+  fn ping_pong() {
+    assert_eq!(get_pan(1, 4, 1., SpreadMode::PingPong), -1.);
+    assert_eq!(get_pan(2, 4, 1., SpreadMode::PingPong), 1.);
+    assert_eq!(get_pan(3, 4, 0.5, SpreadMode::PingPong), -0.5);
+  }
+
+  #[test]
+  fn linear() {
+    assert_eq!(get_pan(1, 4, 1., SpreadMode::Linear), -1.);
+    assert_eq!(get_pan(2, 4, 1., SpreadMode::Linear), 0.);
+    assert_eq!(get_pan(3, 4, 1., SpreadMode::Linear), 1.);
+    assert_eq!(get_pan(1, 2, 1., SpreadMode::Linear), 0.);
+  }
+
+  #[test]
+  fn center_out() {
+    assert_approximately_eq(get_pan(1, 4, 1., SpreadMode::CenterOut), -1. / 3.);
+    assert_approximately_eq(get_pan(2, 4, 1., SpreadMode::CenterOut), 2. / 3.);
+    assert_approximately_eq(get_pan(3, 4, 1., SpreadMode::CenterOut), -1.);
+  }
+
+  #[test]
+  fn random_is_seeded() {
+    let pan = get_pan(3, 8, 1., SpreadMode::Random);
+    assert_eq!(pan, get_pan(3, 8, 1., SpreadMode::Random));
+    assert!((-1. ..=1.).contains(&pan));
+  }
+
+  #[test]
+  fn pan_gains() {
+    let (left, right) = get_pan_gains(0.);
+    assert_approximately_eq(left, 1.);
+    assert_approximately_eq(right, 1.);
+
+    let (left, right) = get_pan_gains(-1.);
+    assert_approximately_eq(left, 2_f32.sqrt());
+    assert_approximately_eq(right, 0.);
+
+    let (left, right) = get_pan_gains(1.);
+    assert_approximately_eq(left, 0.);
+    assert_approximately_eq(right, 2_f32.sqrt());
+  }
+}
@@ -0,0 +1,214 @@
+use crate::{
+  envelope::{get_envelope_gain, EnvelopeShape},
+  feedback::FeedbackMode,
+  pattern::{get_position, CustomTap, Pattern},
+  Params, MAX_REPEATS,
+};
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+/// A single repeat of the input.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Tap {
+  /// The delay time in milliseconds.
+  pub time: f32,
+  pub gain: f32,
+}
+
+/// Returns the number of repeats including the dry signal, which is at most `MAX_REPEATS`.
+/// The custom pattern has as many repeats as there are custom taps.
+pub fn get_repeats(params: &Params, custom_taps: &[CustomTap]) -> usize {
+  match params.pattern {
+    Pattern::Custom => custom_taps.len().min(MAX_REPEATS - 1) + 1,
+    _ => params.repeats.min(MAX_REPEATS),
+  }
+}
+
+/// Returns the time and gain of every tap. The first repeat has no delay time, because it's the dry signal, so it's left out.
+pub fn get_tap_layout<'a>(
+  params: &Params,
+  custom_taps: &'a [CustomTap],
+  custom_envelope: &'a [f32],
+) -> impl Iterator<Item = Tap> + 'a {
+  let params = *params;
+  let repeats = get_repeats(&params, custom_taps);
+
+  (1..repeats).scan(0., move |previous_time, index| {
+    let tap = match params.pattern {
+      Pattern::Custom => {
+        let custom_tap = custom_taps[index - 1];
+        Tap {
+          time: custom_tap.time * params.time,
+          gain: custom_tap.gain,
+        }
+      }
+      pattern => {
+        let time = match pattern {
+          Pattern::Skew => get_delay_time(index as f32, params.time, params.skew, *previous_time),
+          _ => get_position(index, repeats - 1, &params) * params.time,
+        };
+        *previous_time = time;
+        Tap {
+          time,
+          gain: get_gain(index, repeats, &params, custom_envelope),
+        }
+      }
+    };
+    Some(tap)
+  })
+}
+
+fn get_gain(index: usize, repeats: usize, params: &Params, custom_envelope: &[f32]) -> f32 {
+  let Params {
+    feedback,
+    feedback_mode,
+    envelope_shape,
+    ..
+  } = *params;
+
+  match (feedback_mode, envelope_shape) {
+    (FeedbackMode::Simulated, EnvelopeShape::Exponential) => {
+      simulate_feedback(index as f32, feedback, repeats)
+    }
+    (FeedbackMode::Recirculating, EnvelopeShape::Exponential) => 1.,
+    (FeedbackMode::Simulated, _) => {
+      get_shaped_gain(index, repeats, params, custom_envelope) * feedback.abs()
+    }
+    (FeedbackMode::Recirculating, _) => get_shaped_gain(index, repeats, params, custom_envelope),
+  }
+}
+
+/// Negative feedback plays the envelope backwards, so the repeats swell in instead of fading out.
+fn get_shaped_gain(index: usize, repeats: usize, params: &Params, custom_envelope: &[f32]) -> f32 {
+  let taps = repeats - 1;
+  let position = reverse_indices(index as f32 - 1., params.feedback, taps) as usize;
+  get_envelope_gain(position, taps, params.envelope_shape, custom_envelope)
+}
+
+fn reverse_indices(index: f32, input: f32, repeats: usize) -> f32 {
+  if input.signum() == 1. {
+    index
+  } else {
+    repeats as f32 - index - 1.0
+  }
+}
+
+fn simulate_feedback(index: f32, feedback: f32, repeats: usize) -> f32 {
+  let feedback_index = reverse_indices(index, feedback, repeats);
+  let absolute_feedback = feedback.abs();
+  if absolute_feedback == 1. {
+    1.
+  } else {
+    absolute_feedback.powf(feedback_index)
+  }
+}
+
+fn get_delay_time(index: f32, time: f32, skew: f32, previous_time: f32) -> f32 {
+  if skew == 0. {
+    time * index
+  } else {
+    let exponential_skew = skew * skew * if skew < 0. { -0.5 } else { 1. } + 1.;
+    exponential_skew.powf(index - 1.) * time + previous_time
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{get_tap_layout, simulate_feedback, Tap};
+  use crate::{CustomTap, Params, Pattern};
+
+  fn get_times(skew: f32) -> Vec<f32> {
+    let params = Params {
+      time: 100.,
+      repeats: 4,
+      skew,
+      ..Default::default()
+    };
+    get_tap_layout(&params, &[], &[])
+      .map(|tap| tap.time)
+      .collect()
+  }
+
+  #[test]
+  fn feedback() {
+    assert_eq!(simulate_feedback(0.0, 1.0, 4), 1.0);
+    assert_eq!(simulate_feedback(1.0, 1.0, 4), 1.0);
+    assert_eq!(simulate_feedback(2.0, 1.0, 4), 1.0);
+    assert_eq!(simulate_feedback(3.0, 1.0, 4), 1.0);
+
+    assert_eq!(simulate_feedback(0.0, 2.0, 4), 1.0);
+    assert_eq!(simulate_feedback(1.0, 2.0, 4), 2.0);
+    assert_eq!(simulate_feedback(2.0, 2.0, 4), 4.0);
+    assert_eq!(simulate_feedback(3.0, 2.0, 4), 8.0);
+
+    assert_eq!(simulate_feedback(0.0, 0.5, 4), 1.0);
+    assert_eq!(simulate_feedback(1.0, 0.5, 4), 0.5);
+    assert_eq!(simulate_feedback(2.0, 0.5, 4), 0.25);
+    assert_eq!(simulate_feedback(3.0, 0.5, 4), 0.125);
+
+    assert_eq!(simulate_feedback(0.0, -1.0, 4), 1.0);
+    assert_eq!(simulate_feedback(1.0, -1.0, 4), 1.0);
+    assert_eq!(simulate_feedback(2.0, -1.0, 4), 1.0);
+    assert_eq!(simulate_feedback(3.0, -1.0, 4), 1.0);
+
+    assert_eq!(simulate_feedback(0.0, -2.0, 4), 8.0);
+    assert_eq!(simulate_feedback(1.0, -2.0, 4), 4.0);
+    assert_eq!(simulate_feedback(2.0, -2.0, 4), 2.0);
+    assert_eq!(simulate_feedback(3.0, -2.0, 4), 1.0);
+
+    assert_eq!(simulate_feedback(0.0, -0.5, 4), 0.125);
+    assert_eq!(simulate_feedback(1.0, -0.5, 4), 0.25);
+    assert_eq!(simulate_feedback(2.0, -0.5, 4), 0.5);
+    assert_eq!(simulate_feedback(3.0, -0.5, 4), 1.0);
+  }
+
+  #[test]
+  fn delay_time() {
+    assert_eq!(get_times(0.), vec![100., 200., 300.]);
+    assert_eq!(get_times(1.), vec![100., 300., 700.]);
+    assert_eq!(get_times(-1.), vec![100., 150., 175.]);
+  }
+
+  #[test]
+  fn layout() {
+    let params = Params {
+      time: 100.,
+      repeats: 3,
+      feedback: 0.5,
+      ..Default::default()
+    };
+    assert_eq!(
+      get_tap_layout(&params, &[], &[]).collect::<Vec<Tap>>(),
+      vec![
+        Tap {
+          time: 100.,
+          gain: 0.5
+        },
+        Tap {
+          time: 200.,
+          gain: 0.25
+        }
+      ]
+    );
+  }
+
+  #[test]
+  fn custom_layout() {
+    let params = Params {
+      time: 100.,
+      pattern: Pattern::Custom,
+      ..Default::default()
+    };
+    let custom_taps = [CustomTap {
+      time: 1.5,
+      gain: 0.8,
+    }];
+    assert_eq!(
+      get_tap_layout(&params, &custom_taps, &[]).collect::<Vec<Tap>>(),
+      vec![Tap {
+        time: 150.,
+        gain: 0.8
+      }]
+    );
+  }
+}
@@ -0,0 +1,170 @@
+use {
+  crate::{delay_line::DelayLine, shared::sample::Sample},
+  core::array,
+};
+
+/// The number of taps that are summed at once.
+const LANES: usize = 8;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum TapSummation {
+  /// Sums one tap at a time.
+  Scalar,
+  /// Sums the taps in chunks of independent partial sums, which the compiler may vectorize. The samples are still
+  /// gathered one at a time and the remaining taps are summed one at a time.
+  Chunked,
+}
+
+/// The gains of a stereo tap, split up into the gain of each input channel for each output channel.
+#[derive(Default, Clone, Copy)]
+pub struct StereoGains {
+  pub left_to_left: f32,
+  pub right_to_left: f32,
+  pub left_to_right: f32,
+  pub right_to_right: f32,
+}
+
+impl StereoGains {
+  fn apply<T: Sample>(&self, left: T, right: T) -> (T, T) {
+    (
+      left * T::from_f32(self.left_to_left) + right * T::from_f32(self.right_to_left),
+      left * T::from_f32(self.left_to_right) + right * T::from_f32(self.right_to_right),
+    )
+  }
+}
+
+pub fn sum_taps<T: Sample>(
+  delay_line: &DelayLine<T>,
+  offsets: &[usize],
+  gains: &[f32],
+  summation: TapSummation,
+) -> T {
+  match summation {
+    TapSummation::Scalar => sum_taps_scalar(delay_line, offsets, gains),
+    TapSummation::Chunked => {
+      let offset_chunks = offsets.chunks_exact(LANES);
+      let gain_chunks = gains.chunks_exact(LANES);
+      let remainder = sum_taps_scalar(
+        delay_line,
+        offset_chunks.remainder(),
+        gain_chunks.remainder(),
+      );
+
+      let mut sums = [T::zero(); LANES];
+      for (offsets, gains) in offset_chunks.zip(gain_chunks) {
+        let samples: [T; LANES] = array::from_fn(|i| delay_line.read_offset(offsets[i]));
+        for lane in 0..LANES {
+          sums[lane] = sums[lane] + samples[lane] * T::from_f32(gains[lane]);
+        }
+      }
+      sums.into_iter().sum::<T>() + remainder
+    }
+  }
+}
+
+pub fn sum_taps_stereo<T: Sample>(
+  delay_lines: &[DelayLine<T>; 2],
+  offsets: &[usize],
+  gains: &[StereoGains],
+  summation: TapSummation,
+) -> (T, T) {
+  match summation {
+    TapSummation::Scalar => sum_taps_stereo_scalar(delay_lines, offsets, gains),
+    TapSummation::Chunked => {
+      let offset_chunks = offsets.chunks_exact(LANES);
+      let gain_chunks = gains.chunks_exact(LANES);
+      let remainder = sum_taps_stereo_scalar(
+        delay_lines,
+        offset_chunks.remainder(),
+        gain_chunks.remainder(),
+      );
+
+      let mut left_sums = [T::zero(); LANES];
+      let mut right_sums = [T::zero(); LANES];
+      for (offsets, gains) in offset_chunks.zip(gain_chunks) {
+        let left: [T; LANES] = array::from_fn(|i| delay_lines[0].read_offset(offsets[i]));
+        let right: [T; LANES] = array::from_fn(|i| delay_lines[1].read_offset(offsets[i]));
+        for lane in 0..LANES {
+          let (left_output, right_output) = gains[lane].apply(left[lane], right[lane]);
+          left_sums[lane] = left_sums[lane] + left_output;
+          right_sums[lane] = right_sums[lane] + right_output;
+        }
+      }
+      (
+        left_sums.into_iter().sum::<T>() + remainder.0,
+        right_sums.into_iter().sum::<T>() + remainder.1,
+      )
+    }
+  }
+}
+
+fn sum_taps_scalar<T: Sample>(delay_line: &DelayLine<T>, offsets: &[usize], gains: &[f32]) -> T {
+  offsets
+    .iter()
+    .zip(gains)
+    .map(|(offset, gain)| delay_line.read_offset(*offset) * T::from_f32(*gain))
+    .sum()
+}
+
+fn sum_taps_stereo_scalar<T: Sample>(
+  delay_lines: &[DelayLine<T>; 2],
+  offsets: &[usize],
+  gains: &[StereoGains],
+) -> (T, T) {
+  offsets
+    .iter()
+    .zip(gains)
+    .map(|(offset, gains)| {
+      gains.apply(
+        delay_lines[0].read_offset(*offset),
+        delay_lines[1].read_offset(*offset),
+      )
+    })
+    .fold((T::zero(), T::zero()), |sum, (left, right)| {
+      (sum.0 + left, sum.1 + right)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{sum_taps, sum_taps_stereo, StereoGains, TapSummation};
+  use crate::delay_line::DelayLine;
+
+  fn get_delay_line(sign: f32) -> DelayLine<f32> {
+    let mut delay_line = DelayLine::new(128, 1000.);
+    for i in 0..128 {
+      delay_line.write((i as f32 * 0.37).sin() * sign);
+    }
+    delay_line
+  }
+
+  #[test]
+  fn chunked_sum_matches_scalar_sum() {
+    let delay_line = get_delay_line(1.);
+    for taps in [1, 8, 21] {
+      let offsets: Vec<usize> = (1..=taps).map(|i| i * 5).collect();
+      let gains: Vec<f32> = (0..taps).map(|i| 0.9_f32.powi(i as i32)).collect();
+      let scalar = sum_taps(&delay_line, &offsets, &gains, TapSummation::Scalar);
+      let chunked = sum_taps(&delay_line, &offsets, &gains, TapSummation::Chunked);
+      assert!((scalar - chunked).abs() < 1e-5);
+    }
+  }
+
+  #[test]
+  fn stereo_chunked_sum_matches_stereo_scalar_sum() {
+    let delay_lines = [get_delay_line(1.), get_delay_line(-0.5)];
+    let offsets: Vec<usize> = (1..=19).map(|i| i * 6).collect();
+    let gains: Vec<StereoGains> = (0..19)
+      .map(|i| StereoGains {
+        left_to_left: 1. - i as f32 * 0.05,
+        right_to_left: i as f32 * 0.05,
+        left_to_right: 0.25,
+        right_to_right: 0.75,
+      })
+      .collect();
+    let scalar = sum_taps_stereo(&delay_lines, &offsets, &gains, TapSummation::Scalar);
+    let chunked = sum_taps_stereo(&delay_lines, &offsets, &gains, TapSummation::Chunked);
+    assert!((scalar.0 - chunked.0).abs() < 1e-5);
+    assert!((scalar.1 - chunked.1).abs() < 1e-5);
+  }
+}
@@ -0,0 +1,129 @@
+const MAX_INTERVALS: usize = 4;
+const TIMEOUT: f32 = 3000.;
+
+/// Derives a time in milliseconds from the average interval between the last taps.
+/// A new series of taps starts when the time between two taps exceeds the timeout.
+pub struct TapTempo {
+  sample_rate: f32,
+  intervals: [f32; MAX_INTERVALS],
+  interval_count: usize,
+  interval_index: usize,
+  samples_since_tap: Option<usize>,
+  timeout: usize,
+  is_triggered: bool,
+  time: Option<f32>,
+}
+
+impl TapTempo {
+  pub fn new(sample_rate: f32) -> Self {
+    Self {
+      sample_rate,
+      intervals: [0.; MAX_INTERVALS],
+      interval_count: 0,
+      interval_index: 0,
+      samples_since_tap: None,
+      timeout: (TIMEOUT * 0.001 * sample_rate) as usize,
+      is_triggered: false,
+      time: None,
+    }
+  }
+
+  /// Call this once for every block of samples. A tap is registered when the trigger goes from false to true.
+  pub fn process(&mut self, trigger: bool, sample_count: usize) -> Option<f32> {
+    if trigger && !self.is_triggered {
+      self.tap();
+    }
+    self.is_triggered = trigger;
+    self.samples_since_tap = self
+      .samples_since_tap
+      .map(|samples_since_tap| samples_since_tap.saturating_add(sample_count));
+
+    self.time
+  }
+
+  /// Returns the tapped time in milliseconds, if any.
+  pub fn get_time(&self) -> Option<f32> {
+    self.time
+  }
+
+  /// Returns the tapped tempo in beats per minute, if any.
+  pub fn get_tempo(&self) -> Option<f32> {
+    self.time.map(|time| 60000. / time)
+  }
+
+  /// Forgets the tapped time, for example when the time is set by other means.
+  pub fn reset(&mut self) {
+    self.interval_count = 0;
+    self.samples_since_tap = None;
+    self.time = None;
+  }
+
+  fn tap(&mut self) {
+    match self.samples_since_tap {
+      Some(samples_since_tap) if samples_since_tap <= self.timeout => {
+        self.intervals[self.interval_index] = samples_since_tap as f32 / self.sample_rate * 1000.;
+        self.interval_index = (self.interval_index + 1) % MAX_INTERVALS;
+        self.interval_count = (self.interval_count + 1).min(MAX_INTERVALS);
+        let sum: f32 = self.intervals.iter().take(self.interval_count).sum();
+        self.time = Some(sum / self.interval_count as f32);
+      }
+      _ => {
+        self.interval_count = 0;
+        self.interval_index = 0;
+      }
+    }
+    self.samples_since_tap = Some(0);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::TapTempo;
+
+  #[test]
+  fn should_average_intervals_between_taps() {
+    let mut tap_tempo = TapTempo::new(1000.);
+
+    assert_eq!(tap_tempo.process(true, 100), None);
+    assert_eq!(tap_tempo.process(false, 400), None);
+    assert_eq!(tap_tempo.process(true, 100), Some(500.));
+    assert_eq!(tap_tempo.process(false, 600), Some(500.));
+    assert_eq!(tap_tempo.process(true, 100), Some(600.));
+    assert_eq!(tap_tempo.get_tempo(), Some(100.));
+  }
+
+  #[test]
+  fn should_only_register_rising_edges() {
+    let mut tap_tempo = TapTempo::new(1000.);
+
+    tap_tempo.process(true, 250);
+    tap_tempo.process(true, 250);
+    assert_eq!(tap_tempo.process(false, 250), None);
+    assert_eq!(tap_tempo.process(true, 250), Some(750.));
+  }
+
+  #[test]
+  fn should_start_over_after_timeout() {
+    let mut tap_tempo = TapTempo::new(1000.);
+
+    tap_tempo.process(true, 100);
+    tap_tempo.process(false, 100);
+    assert_eq!(tap_tempo.process(true, 100), Some(200.));
+    tap_tempo.process(false, 5000);
+    assert_eq!(tap_tempo.process(true, 100), Some(200.));
+    tap_tempo.process(false, 300);
+    assert_eq!(tap_tempo.process(true, 100), Some(400.));
+  }
+
+  #[test]
+  fn should_forget_time_on_reset() {
+    let mut tap_tempo = TapTempo::new(1000.);
+
+    tap_tempo.process(true, 100);
+    tap_tempo.process(false, 100);
+    tap_tempo.process(true, 100);
+    tap_tempo.reset();
+    assert_eq!(tap_tempo.get_time(), None);
+    assert_eq!(tap_tempo.process(false, 100), None);
+  }
+}
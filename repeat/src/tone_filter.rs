@@ -0,0 +1,114 @@
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+use {crate::shared::sample::Sample, core::f32::consts::TAU};
+
+const LOWPASS_RANGE: (f32, f32) = (20000., 200.);
+const HIGHPASS_RANGE: (f32, f32) = (20., 4000.);
+
+/// A one-pole filter for a single tap. Negative tone values darken the tap with a lowpass filter,
+/// positive tone values thin it out with a highpass filter.
+#[derive(Clone, Copy)]
+pub struct ToneFilter<T> {
+  b1: T,
+  is_highpass: bool,
+  is_bypassed: bool,
+  z: [T; 2],
+}
+
+impl<T: Sample> ToneFilter<T> {
+  pub fn new(sample_rate: f32, index: usize, repeats: usize, tone: f32, tone_skew: f32) -> Self {
+    let is_bypassed = tone == 0. || repeats < 2;
+    let freq = if is_bypassed {
+      0.
+    } else {
+      Self::get_freq(index, repeats, tone, tone_skew)
+    };
+
+    Self {
+      b1: T::from_f32((-TAU * freq / sample_rate).exp()),
+      is_highpass: tone > 0.,
+      is_bypassed,
+      z: [T::zero(); 2],
+    }
+  }
+
+  pub fn is_bypassed(&self) -> bool {
+    self.is_bypassed
+  }
+
+  pub fn process(&mut self, input: T, channel: usize) -> T {
+    if self.is_bypassed {
+      return input;
+    }
+
+    let z = &mut self.z[channel];
+    *z = input + (*z - input) * self.b1;
+    if self.is_highpass {
+      input - *z
+    } else {
+      *z
+    }
+  }
+
+  /// The cutoff frequency progresses logarithmically over the taps. Tone skew bends this progression, so the
+  /// filtering either sets in with the first repeats (positive values) or is saved for the last repeats (negative values).
+  fn get_freq(index: usize, repeats: usize, tone: f32, tone_skew: f32) -> f32 {
+    let progress = index as f32 / (repeats - 1) as f32;
+    let exponent = 4_f32.powf(-tone_skew);
+    let amount = progress.powf(exponent) * tone.abs();
+    let (start, end) = if tone > 0. {
+      HIGHPASS_RANGE
+    } else {
+      LOWPASS_RANGE
+    };
+
+    start * (end / start).powf(amount)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ToneFilter;
+
+  fn assert_approximately_eq(left: f32, right: f32) {
+    assert!(
+      (left - right).abs() < right * 1e-4,
+      "left: {left}, right: {right}"
+    );
+  }
+
+  #[test]
+  fn cutoff_progresses_by_tap_index() {
+    assert_approximately_eq(ToneFilter::<f32>::get_freq(3, 4, -1., 0.), 200.);
+    assert_approximately_eq(ToneFilter::<f32>::get_freq(3, 4, 1., 0.), 4000.);
+    assert_approximately_eq(ToneFilter::<f32>::get_freq(3, 4, -0.5, 0.), 2000.);
+    assert!(
+      ToneFilter::<f32>::get_freq(1, 4, -1., 0.) > ToneFilter::<f32>::get_freq(2, 4, -1., 0.)
+    );
+  }
+
+  #[test]
+  fn tone_skew_bends_the_progression() {
+    let freq = ToneFilter::<f32>::get_freq(1, 4, -1., 0.);
+    assert!(ToneFilter::<f32>::get_freq(1, 4, -1., 1.) < freq);
+    assert!(ToneFilter::<f32>::get_freq(1, 4, -1., -1.) > freq);
+  }
+
+  #[test]
+  fn should_bypass_when_tone_is_zero() {
+    let mut tone_filter = ToneFilter::<f32>::new(44100., 1, 4, 0., 0.);
+    assert_eq!(tone_filter.process(0.5, 0), 0.5);
+  }
+
+  #[test]
+  fn lowpass_passes_dc_and_highpass_blocks_it() {
+    let mut lowpass = ToneFilter::<f32>::new(44100., 3, 4, -1., 0.);
+    let mut highpass = ToneFilter::<f32>::new(44100., 3, 4, 1., 0.);
+    for _ in 0..44100 {
+      lowpass.process(1., 0);
+      highpass.process(1., 0);
+    }
+    assert!((lowpass.process(1., 0) - 1.).abs() < 1e-4);
+    assert!(highpass.process(1., 0).abs() < 1e-4);
+  }
+}